@@ -0,0 +1,386 @@
+//! 行级差异比较：Myers 算法（线性空间版本）以及 unified diff 格式输出
+
+use std::ops::Range;
+
+/**
+ * 编辑脚本中的一步，下标均从0开始
+ * - Equal(i, j)：旧序列第i行与新序列第j行相同
+ * - Delete(i)：删除旧序列第i行
+ * - Insert(j)：插入新序列第j行
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/**
+ * 'myers'计算把a变为b的最短编辑脚本，结果按照旧、新序列的顺序排列
+ * # 示例
+ * ```
+    use minigit::diff::{myers, Edit};
+    let edits = myers(&["a", "b"], &["a", "c"]);
+    assert_eq!(edits, vec![Edit::Equal(0, 0), Edit::Delete(1), Edit::Insert(1)]);
+ * ```
+ */
+pub fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Edit> {
+    let mut edits = Vec::with_capacity(a.len().max(b.len()));
+    diff_range(a, 0..a.len(), b, 0..b.len(), &mut edits);
+    edits
+}
+
+fn diff_range<T: PartialEq>(a: &[T], mut ra: Range<usize>, b: &[T], mut rb: Range<usize>, edits: &mut Vec<Edit>) {
+    // 先去掉公共前缀和公共后缀
+    while ra.start < ra.end && rb.start < rb.end && a[ra.start] == b[rb.start] {
+        edits.push(Edit::Equal(ra.start, rb.start));
+        ra.start += 1;
+        rb.start += 1;
+    }
+    let mut suffix = 0;
+    while ra.start < ra.end - suffix && rb.start < rb.end - suffix && a[ra.end - suffix - 1] == b[rb.end - suffix - 1] {
+        suffix += 1;
+    }
+    ra.end -= suffix;
+    rb.end -= suffix;
+    if ra.is_empty() {
+        edits.extend(rb.clone().map(Edit::Insert));
+    }
+    else if rb.is_empty() {
+        edits.extend(ra.clone().map(Edit::Delete));
+    }
+    else {
+        let (x0, y0, x1, y1) = middle_snake(a, ra.clone(), b, rb.clone());
+        diff_range(a, ra.start..x0, b, rb.start..y0, edits);
+        edits.extend((x0..x1).zip(y0..y1).map(|(i, j)| Edit::Equal(i, j)));
+        diff_range(a, x1..ra.end, b, y1..rb.end, edits);
+    }
+    edits.extend((0..suffix).map(|i| Edit::Equal(ra.end + i, rb.end + i)));
+}
+
+/**
+ * 同时从头部和尾部进行搜索，找到最短编辑路径中间的一段蛇形（对角线）路径，返回其在a、b中的起止下标
+ */
+fn middle_snake<T: PartialEq>(a: &[T], ra: Range<usize>, b: &[T], rb: Range<usize>) -> (usize, usize, usize, usize) {
+    let n = ra.len() as isize;
+    let m = rb.len() as isize;
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    let mut forward = vec![0isize; (2 * offset + 1) as usize];
+    let mut backward = vec![0isize; (2 * offset + 1) as usize];
+    let fa = |x: isize| &a[ra.start + x as usize];
+    let fb = |y: isize| &b[rb.start + y as usize];
+    let ba = |x: isize| &a[ra.end - 1 - x as usize];
+    let bb = |y: isize| &b[rb.end - 1 - y as usize];
+    for d in 0..=max {
+        let mut k = -d;
+        while k <= d {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && forward[i - 1] < forward[i + 1]) { forward[i + 1] } else { forward[i - 1] + 1 };
+            let mut y = x - k;
+            let (sx, sy) = (x, y);
+            while x < n && y < m && fa(x) == fb(y) {
+                x += 1;
+                y += 1;
+            }
+            forward[i] = x;
+            let kb = delta - k;
+            if odd && kb > -d && kb < d && x + backward[(kb + offset) as usize] >= n {
+                return (ra.start + sx as usize, rb.start + sy as usize, ra.start + x as usize, rb.start + y as usize);
+            }
+            k += 2;
+        }
+        let mut k = -d;
+        while k <= d {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && backward[i - 1] < backward[i + 1]) { backward[i + 1] } else { backward[i - 1] + 1 };
+            let mut y = x - k;
+            let (sx, sy) = (x, y);
+            while x < n && y < m && ba(x) == bb(y) {
+                x += 1;
+                y += 1;
+            }
+            backward[i] = x;
+            let kf = delta - k;
+            if !odd && kf >= -d && kf <= d && x + forward[(kf + offset) as usize] >= n {
+                return (ra.start + (n - x) as usize, rb.start + (m - y) as usize, ra.start + (n - sx) as usize, rb.start + (m - sy) as usize);
+            }
+            k += 2;
+        }
+    }
+    unreachable!("middle snake must exist within (n + m + 1) / 2 steps");
+}
+
+
+/**
+ * unified diff 中的一个区块，start 为从1开始的行号（长度为0时为区块前一行的行号）
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub edits: Vec<Edit>,
+}
+
+/**
+ * 'hunks'将编辑脚本按照上下文行数context分组，距离不超过2 * context行的修改会合并到同一个区块中
+ */
+pub fn hunks(edits: &[Edit], context: usize) -> Vec<Hunk> {
+    let changes = edits.iter().enumerate()
+                       .filter(|(_, e)| !matches!(e, Edit::Equal(..)))
+                       .map(|(i, _)| i)
+                       .collect::<Vec<usize>>();
+    let mut re = Vec::new();
+    let mut it = 0;
+    while it < changes.len() {
+        let first = changes[it];
+        let mut last = first;
+        it += 1;
+        while it < changes.len() && changes[it] - last - 1 <= 2 * context {
+            last = changes[it];
+            it += 1;
+        }
+        let start = first.saturating_sub(context);
+        let end = (last + context + 1).min(edits.len());
+        let slice = &edits[start..end];
+        // 区块起始行号由区块之前的编辑统计得到
+        let (mut old_before, mut new_before) = (0, 0);
+        for e in &edits[..start] {
+            match e {
+                Edit::Equal(..) => { old_before += 1; new_before += 1; },
+                Edit::Delete(_) => old_before += 1,
+                Edit::Insert(_) => new_before += 1,
+            }
+        }
+        let old_len = slice.iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let new_len = slice.iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+        re.push(Hunk {
+            old_start: if old_len == 0 { old_before } else { old_before + 1 },
+            old_len,
+            new_start: if new_len == 0 { new_before } else { new_before + 1 },
+            new_len,
+            edits: slice.to_vec(),
+        });
+    }
+    re
+}
+
+/**
+ * 将内容按行切分，每一行保留结尾的换行符，最后一行可能没有换行符
+ */
+pub fn split_lines(value: &[u8]) -> Vec<&[u8]> {
+    value.split_inclusive(|&b| b == b'\n').collect()
+}
+
+fn range_header(start: usize, len: usize) -> String {
+    if len == 1 {
+        format!("{start}")
+    }
+    else {
+        format!("{start},{len}")
+    }
+}
+
+fn push_line(out: &mut Vec<u8>, tag: u8, line: &[u8]) {
+    out.push(tag);
+    out.extend_from_slice(line);
+    if !line.ends_with(b"\n") {
+        out.extend_from_slice(b"\n\\ No newline at end of file\n");
+    }
+}
+
+/**
+ * 'unified_diff'比较old和new两段内容，输出带有context行上下文的unified diff区块（不包含 ---/+++ 文件头）
+ * # 示例
+ * ```
+    let out = minigit::diff::unified_diff(b"a\nb\nc\n", b"a\nB\nc\n", 3);
+    assert_eq!(out, b"@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n".to_vec());
+ * ```
+ */
+pub fn unified_diff(old: &[u8], new: &[u8], context: usize) -> Vec<u8> {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let edits = myers(&old_lines, &new_lines);
+    let mut out = Vec::new();
+    for hunk in hunks(&edits, context) {
+        out.extend_from_slice(format!("@@ -{} +{} @@\n", range_header(hunk.old_start, hunk.old_len), range_header(hunk.new_start, hunk.new_len)).as_bytes());
+        for e in hunk.edits {
+            match e {
+                Edit::Equal(i, _) => push_line(&mut out, b' ', old_lines[i]),
+                Edit::Delete(i) => push_line(&mut out, b'-', old_lines[i]),
+                Edit::Insert(j) => push_line(&mut out, b'+', new_lines[j]),
+            }
+        }
+    }
+    out
+}
+
+
+
+/**
+ * 对于a中的每一行，记录它在最短编辑脚本中与b中对应的行（没有对应时为None）
+ */
+fn match_lines<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Option<usize>> {
+    let mut re = vec![None; a.len()];
+    for e in myers(a, b) {
+        if let Edit::Equal(i, j) = e {
+            re[i] = Some(j);
+        }
+    }
+    re
+}
+
+fn push_lines(out: &mut Vec<u8>, lines: &[&[u8]]) {
+    for line in lines {
+        out.extend_from_slice(line);
+    }
+}
+
+fn push_marker(out: &mut Vec<u8>, marker: &str, label: &str) {
+    if !out.is_empty() && !out.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+    if label.is_empty() {
+        out.extend_from_slice(format!("{marker}\n").as_bytes());
+    }
+    else {
+        out.extend_from_slice(format!("{marker} {label}\n").as_bytes());
+    }
+}
+
+/**
+ * 'merge3'以base为共同祖先对ours和theirs进行diff3式的三路合并，返回（合并结果，是否没有冲突）
+ * 只被一方修改的区块自动采用修改后的内容，双方都修改且结果不同的区块使用
+ * <<<<<<< / ||||||| / ======= / >>>>>>> 标出，labels依次为ours、base、theirs的名字
+ * # 示例
+ * ```
+    let (value, no_conflict) = minigit::diff::merge3(b"a\nb\nc\n", b"A\nb\nc\n", b"a\nb\nC\n", ["ours", "base", "theirs"]);
+    assert!(no_conflict);
+    assert_eq!(value, b"A\nb\nC\n".to_vec());
+ * ```
+ */
+pub fn merge3(base: &[u8], ours: &[u8], theirs: &[u8], labels: [&str; 3]) -> (Vec<u8>, bool) {
+    let base = split_lines(base);
+    let ours = split_lines(ours);
+    let theirs = split_lines(theirs);
+    let match_ours = match_lines(&base, &ours);
+    let match_theirs = match_lines(&base, &theirs);
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    let mut out = Vec::new();
+    let mut no_conflict = true;
+    loop {
+        // 三方都相同的稳定区块
+        while i < base.len() && match_ours[i] == Some(j) && match_theirs[i] == Some(k) {
+            out.extend_from_slice(base[i]);
+            i += 1;
+            j += 1;
+            k += 1;
+        }
+        if i == base.len() && j == ours.len() && k == theirs.len() {
+            break;
+        }
+        // 找到下一个三方都相同的行，中间的部分为不稳定区块
+        let (ni, nj, nk) = match (i..base.len()).find(|&n| match_ours[n].is_some() && match_theirs[n].is_some()) {
+            Some(n)=> (n, match_ours[n].unwrap(), match_theirs[n].unwrap()),
+            None=> (base.len(), ours.len(), theirs.len()),
+        };
+        let (b, o, t) = (&base[i..ni], &ours[j..nj], &theirs[k..nk]);
+        if o == b || o == t {
+            push_lines(&mut out, t);
+        }
+        else if t == b {
+            push_lines(&mut out, o);
+        }
+        else {
+            no_conflict = false;
+            push_marker(&mut out, "<<<<<<<", labels[0]);
+            push_lines(&mut out, o);
+            push_marker(&mut out, "|||||||", labels[1]);
+            push_lines(&mut out, b);
+            push_marker(&mut out, "=======", "");
+            push_lines(&mut out, t);
+            push_marker(&mut out, ">>>>>>>", labels[2]);
+        }
+        (i, j, k) = (ni, nj, nk);
+    }
+    (out, no_conflict)
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn apply<T: Clone + PartialEq>(a: &[T], b: &[T], edits: &[Edit]) -> Vec<T> {
+        let mut re = Vec::new();
+        for e in edits {
+            match *e {
+                Edit::Equal(i, j) => { assert!(a[i] == b[j]); re.push(a[i].clone()); },
+                Edit::Insert(j) => re.push(b[j].clone()),
+                Edit::Delete(_) => {},
+            }
+        }
+        re
+    }
+
+    #[test]
+    fn test_myers_shortest() {
+        let a = "ABCABBA".chars().collect::<Vec<char>>();
+        let b = "CBABAC".chars().collect::<Vec<char>>();
+        let edits = myers(&a, &b);
+        assert_eq!(apply(&a, &b, &edits), b);
+        let changes = edits.iter().filter(|e| !matches!(e, Edit::Equal(..))).count();
+        assert_eq!(changes, 5);
+    }
+
+    #[test]
+    fn test_myers_edge() {
+        let empty: Vec<u8> = Vec::new();
+        assert_eq!(myers(&empty, &empty), vec![]);
+        assert_eq!(myers(&empty, &[1, 2]), vec![Edit::Insert(0), Edit::Insert(1)]);
+        assert_eq!(myers(&[1, 2], &empty), vec![Edit::Delete(0), Edit::Delete(1)]);
+        let a = (0..200).map(|i| i % 7).collect::<Vec<i32>>();
+        let b = (0..180).map(|i| i % 5).collect::<Vec<i32>>();
+        let edits = myers(&a, &b);
+        assert_eq!(apply(&a, &b, &edits), b);
+        assert_eq!(edits.iter().filter(|e| !matches!(e, Edit::Insert(_))).count(), a.len());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = b"1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = b"1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\neleven";
+        let out = String::from_utf8(unified_diff(old, new, 1)).unwrap();
+        assert_eq!(out, "@@ -2,3 +2,3 @@\n 2\n-3\n+three\n 4\n@@ -10 +10,2 @@\n 10\n+eleven\n\\ No newline at end of file\n");
+        assert!(unified_diff(old, old, 3).is_empty());
+        assert_eq!(unified_diff(b"", b"a\n", 3), b"@@ -0,0 +1 @@\n+a\n".to_vec());
+    }
+
+    #[test]
+    fn test_merge3_clean() {
+        let base = b"1\n2\n3\n4\n5\n";
+        let ours = b"one\n2\n3\n4\n5\n";
+        let theirs = b"1\n2\n3\n4\nfive\nsix\n";
+        let labels = ["ours", "base", "theirs"];
+        assert_eq!(merge3(base, ours, theirs, labels), (b"one\n2\n3\n4\nfive\nsix\n".to_vec(), true));
+        assert_eq!(merge3(base, base, theirs, labels), (theirs.to_vec(), true));
+        assert_eq!(merge3(base, ours, ours, labels), (ours.to_vec(), true));
+    }
+
+    #[test]
+    fn test_merge3_conflict() {
+        let base = b"a\nb\nc\nd\n";
+        let ours = b"a\nB1\nc\nD\n";
+        let theirs = b"a\nB2\nc\nd";
+        let (value, no_conflict) = merge3(base, ours, theirs, ["master", "common ancestor", "dev"]);
+        assert!(!no_conflict);
+        let expect = "a\n<<<<<<< master\nB1\n||||||| common ancestor\nb\n=======\nB2\n>>>>>>> dev\nc\n\
+                      <<<<<<< master\nD\n||||||| common ancestor\nd\n=======\nd\n>>>>>>> dev\n";
+        assert_eq!(String::from_utf8(value).unwrap(), expect);
+    }
+}
@@ -0,0 +1,154 @@
+//! minigit 错误类型：库中所有操作都返回 minigit::Result
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/**
+ * minigit操作失败的原因，携带出错的路径、key或分支名字，调用者可以据此匹配不同的失败
+ * - Usage：命令行参数错误
+ * - NotARepository：路径不在minigit仓库中
+ * - OutsideRepository：路径在仓库以外
+ * - InvalidPath：路径不存在、以'..'结尾或者位于'.minigit'中
+ * - UnsupportedFile：不支持的文件类型（例如管道、设备文件）
+ * - IgnoredPath：路径被忽略规则忽略，并且没有被暂存区跟踪
+ * - ObjectNotFound：objects中没有这个key对应的对象
+ * - CorruptObject：对象内容格式错误，key为None时表示还没有写入objects的对象
+ * - CorruptIndex：index文件格式错误
+ * - CorruptConfig：config文件格式错误
+ * - CorruptReflog：引用日志格式错误
+ * - BranchExists / BranchNotFound：分支已经存在 / 不存在
+ * - DeleteCurrentBranch：不能删除当前分支
+ * - NoCommits：分支还没有任何提交
+ * - DetachedHead：HEAD直接指向一个提交而不是分支，值为这个提交的key
+ * - TagExists / TagNotFound：标签已经存在 / 不存在
+ * - UnknownRevision：版本表达式找不到对应的对象，例如既不是分支、标签也不是提交的key，或者祖先提交不存在
+ * - AmbiguousRevision：缩写的key对应多个对象
+ * - NoCommonAncestor：两个提交没有公共祖先，不能合并
+ * - MergeConflict：合并有冲突，值为有冲突的文件
+ * - WouldOverwrite：切换分支或者合并会覆盖没有提交的修改或者未跟踪的文件，值为这些路径
+ * - Locked：文件正在被其他minigit进程修改，值为锁文件的路径
+ * - Io：读写文件系统失败
+ */
+#[derive(Debug)]
+pub enum Error {
+    Usage(String),
+    NotARepository(PathBuf),
+    OutsideRepository(PathBuf),
+    InvalidPath(PathBuf),
+    UnsupportedFile(PathBuf),
+    IgnoredPath(PathBuf),
+    ObjectNotFound(String),
+    CorruptObject { key: Option<String>, reason: String },
+    CorruptIndex(String),
+    CorruptConfig(String),
+    CorruptReflog(String),
+    BranchExists(String),
+    BranchNotFound(String),
+    DeleteCurrentBranch(String),
+    NoCommits(String),
+    DetachedHead(String),
+    TagExists(String),
+    TagNotFound(String),
+    UnknownRevision(String),
+    AmbiguousRevision(String),
+    NoCommonAncestor(String, String),
+    MergeConflict(Vec<PathBuf>),
+    WouldOverwrite(Vec<PathBuf>),
+    Locked(PathBuf),
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /**
+     * 'corrupt'生成解析对象失败的错误，还不知道对象的key
+     */
+    pub(crate) fn corrupt(reason: impl Into<String>) -> Error {
+        Error::CorruptObject { key: None, reason: reason.into() }
+    }
+
+    /**
+     * 'with_key'为解析对象失败的错误补上对象的key
+     */
+    pub(crate) fn with_key(self, key: &str) -> Error {
+        match self {
+            Error::CorruptObject { key: None, reason } => Error::CorruptObject { key: Some(key.to_string()), reason },
+            other => other,
+        }
+    }
+
+    /**
+     * 'exit_code'返回命令行程序遇到这个错误时的退出码
+     * # 示例
+     * ```
+        let err = minigit::Error::BranchNotFound("dev".to_string());
+        assert_eq!(err.exit_code(), 5);
+        assert_eq!(err.to_string(), "no such branch named dev");
+     * ```
+     */
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::MergeConflict(_) | Error::WouldOverwrite(_) => 1,
+            Error::Usage(_) => 2,
+            Error::NotARepository(_) => 3,
+            Error::OutsideRepository(_) | Error::InvalidPath(_) | Error::UnsupportedFile(_)
+            | Error::IgnoredPath(_) => 4,
+            Error::BranchExists(_) | Error::BranchNotFound(_) | Error::DeleteCurrentBranch(_)
+            | Error::NoCommits(_) | Error::DetachedHead(_) | Error::TagExists(_) | Error::TagNotFound(_)
+            | Error::UnknownRevision(_) | Error::AmbiguousRevision(_) | Error::NoCommonAncestor(_, _) => 5,
+            Error::ObjectNotFound(_) | Error::CorruptObject { .. } | Error::CorruptIndex(_)
+            | Error::CorruptConfig(_) | Error::CorruptReflog(_) => 6,
+            Error::Locked(_) | Error::Io(_) => 7,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) => write!(f, "{message}"),
+            Error::NotARepository(path) => write!(f, "{} is not a minigit repository", path.display()),
+            Error::OutsideRepository(path) => write!(f, "path {} is outside repository", path.display()),
+            Error::InvalidPath(path) => write!(f, "invaild path {}", path.display()),
+            Error::UnsupportedFile(path) => write!(f, "unsupported file type at {}", path.display()),
+            Error::IgnoredPath(path) => write!(f, "path {} is ignored by one of your ignore files", path.display()),
+            Error::ObjectNotFound(key) => write!(f, "object {key} not found"),
+            Error::CorruptObject { key: Some(key), reason } => write!(f, "corrupt object {key}: {reason}"),
+            Error::CorruptObject { key: None, reason } => write!(f, "corrupt object: {reason}"),
+            Error::CorruptIndex(reason) => write!(f, "corrupt index: {reason}"),
+            Error::CorruptConfig(reason) => write!(f, "corrupt config: {reason}"),
+            Error::CorruptReflog(reason) => write!(f, "corrupt reflog: {reason}"),
+            Error::BranchExists(name) => write!(f, "branch {name} is existing, you can't create a existing branch"),
+            Error::BranchNotFound(name) => write!(f, "no such branch named {name}"),
+            Error::DeleteCurrentBranch(name) => write!(f, "can't delete now branch {name}"),
+            Error::NoCommits(name) => write!(f, "branch {name} doesn't have any commits yet"),
+            Error::DetachedHead(key) => write!(f, "HEAD is detached at {key}, not on any branch"),
+            Error::TagExists(name) => write!(f, "tag {name} is existing, you can't create a existing tag"),
+            Error::TagNotFound(name) => write!(f, "no such tag named {name}"),
+            Error::UnknownRevision(name) => write!(f, "unknown revision {name}, it is not a branch, a tag or a commit"),
+            Error::AmbiguousRevision(name) => write!(f, "short key {name} is ambiguous, use more characters"),
+            Error::NoCommonAncestor(name1, name2) => write!(f, "branch {name1} and branch {name2} have no common ancestor commit"),
+            Error::MergeConflict(paths) => write!(f, "merge conflict in {} file(s), fix conflicts and then commit the result", paths.len()),
+            Error::WouldOverwrite(paths) => write!(f, "local changes in {} path(s) would be overwritten, commit or remove them first, or use --force", paths.len()),
+            Error::Locked(path) => write!(f, "unable to create {}: another minigit process seems to be running in this repository, remove the lock file if it has exited", path.display()),
+            Error::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,74 @@
+//! minigit 哈希算法：对象的key由对象内容经过哈希算法得到
+
+use crypto::{digest::Digest, sha1::Sha1, sha2::Sha256};
+
+/**
+ * 计算对象key的哈希算法，在'init'时选择
+ * - Sha1：key为40位十六进制字符串
+ * - Sha256：key为64位十六进制字符串
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<HashAlgorithm> {
+        match name {
+            "sha1" => Some(HashAlgorithm::Sha1),
+            "sha256" => Some(HashAlgorithm::Sha256),
+            _ => None,
+        }
+    }
+
+    /**
+     * 'from_key_len'根据十六进制key的长度判断使用的哈希算法
+     */
+    pub fn from_key_len(len: usize) -> Option<HashAlgorithm> {
+        [HashAlgorithm::Sha1, HashAlgorithm::Sha256].into_iter().find(|hash| hash.hex_len() == len)
+    }
+
+    /**
+     * 'hex_len'返回key的十六进制长度，二进制长度为它的一半
+     */
+    pub fn hex_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Sha256 => 64,
+        }
+    }
+
+    /**
+     * 'hash'将value中的数据使用哈希算法加密成key
+     * # 示例
+     * ```
+        use minigit::HashAlgorithm;
+        assert_eq!(HashAlgorithm::Sha1.hash(b"").len(), 40);
+        assert_eq!(HashAlgorithm::Sha256.hash(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+     * ```
+     */
+    pub fn hash(&self, value: &[u8]) -> String {
+        let mut hasher: Box<dyn Digest> = match self {
+            HashAlgorithm::Sha1 => Box::new(Sha1::new()),
+            HashAlgorithm::Sha256 => Box::new(Sha256::new()),
+        };
+        hasher.input(value);
+        hasher.result_str()
+    }
+
+    /**
+     * 'is_valid_key'检查key是否为这种算法得到的合法key（小写十六进制字符串）
+     */
+    pub fn is_valid_key(&self, key: &str) -> bool {
+        key.len() == self.hex_len() && key.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    }
+}
@@ -0,0 +1,262 @@
+//! minigit 忽略规则：'.minigitignore'文件和'.minigit/info/exclude'中的规则决定哪些未跟踪的文件被忽略
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+
+/**
+ * 每个文件夹中保存忽略规则的文件名
+ */
+pub const IGNORE_FILE: &str = ".minigitignore";
+
+/**
+ * 一条忽略规则，规则的格式与gitignore相同
+ * - 空行和以'#'开头的行不是规则
+ * - 以'!'开头的规则重新包含之前被忽略的文件
+ * - 以'/'结尾的规则只匹配文件夹
+ * - 中间或开头含有'/'的规则匹配相对于规则文件所在文件夹的路径，否则匹配任意层级中的文件名
+ * - '*'匹配除'/'以外的任意字符，'?'匹配一个字符，'[a-z]'匹配字符集合，'**'匹配任意层文件夹
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreRule {
+    pub pattern: String,
+    pub negated: bool,
+    pub dir_only: bool,
+    pub anchored: bool,
+}
+
+impl IgnoreRule {
+    /**
+     * 'parse'解析规则文件中的一行，不是规则时返回None
+     */
+    pub fn parse(line: &str) -> Option<IgnoreRule> {
+        let mut line = line.trim_end_matches('\r');
+        // 行末的空格被忽略，除非使用'\'转义
+        while line.ends_with(' ') && !line.ends_with("\\ ") {
+            line = &line[..line.len() - 1];
+        }
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let pattern = line.strip_prefix('/').unwrap_or(line);
+        if pattern.is_empty() {
+            return None;
+        }
+        Some(IgnoreRule { pattern: pattern.to_string(), negated, dir_only, anchored })
+    }
+
+    /**
+     * 'matches'检查相对于规则文件所在文件夹、以'/'分隔的路径relative是否匹配这条规则（不考虑'!'）
+     */
+    pub fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let text = if self.anchored {
+            relative
+        }
+        else {
+            relative.rsplit('/').next().unwrap_or(relative)
+        };
+        glob_match(self.pattern.as_bytes(), text.as_bytes())
+    }
+}
+
+/**
+ * 解析'[...]'字符集合，返回是否匹配字符c以及集合在pattern中的长度，没有结尾的']'时返回None
+ */
+fn match_class(pattern: &[u8], c: u8) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some(b'!') | Some(b'^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while let Some(&start) = pattern.get(i) {
+        if start == b']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&b'-') && pattern.get(i + 2).is_some_and(|&end| end != b']') {
+            matched |= (start..=pattern[i + 2]).contains(&c);
+            i += 3;
+        }
+        else {
+            matched |= start == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+/**
+ * 'glob_match'检查text是否匹配通配符pattern，'*'和'?'不匹配'/'
+ */
+pub fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') if pattern.starts_with(b"**") => {
+            let rest = &pattern[2..];
+            match rest.strip_prefix(b"/") {
+                // '**/'匹配零层或者多层文件夹
+                Some(rest) => glob_match(rest, text)
+                    || text.iter().enumerate().any(|(i, &c)| c == b'/' && glob_match(rest, &text[i + 1..])),
+                // 其他位置的'**'匹配任意内容
+                None => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+            }
+        },
+        Some(b'*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        },
+        Some(b'?') => text.first().is_some_and(|&c| c != b'/') && glob_match(&pattern[1..], &text[1..]),
+        Some(b'[') => match (text.first(), match_class(pattern, text.first().copied().unwrap_or(0))) {
+            (Some(&c), Some((matched, len))) => c != b'/' && matched && glob_match(&pattern[len..], &text[1..]),
+            (None, Some(_)) => false,
+            // 没有结尾的']'时'['只是普通字符
+            (_, None) => text.first() == Some(&b'[') && glob_match(&pattern[1..], &text[1..]),
+        },
+        Some(b'\\') if pattern.len() > 1 => text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/**
+ * 仓库中所有的忽略规则，'.minigit/info/exclude'中的规则优先级最低，更深的文件夹中的'.minigitignore'优先级更高
+ * 同一个文件中后面的规则优先，文件夹被忽略时其中的所有文件都被忽略
+ */
+#[derive(Debug)]
+pub struct Ignore {
+    root_path: PathBuf,
+    exclude: Vec<IgnoreRule>,
+    // 已经读取过的各个文件夹中的规则
+    cache: RefCell<HashMap<PathBuf, Vec<IgnoreRule>>>,
+}
+
+fn read_rules(path: &Path) -> Result<Vec<IgnoreRule>> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_to_string(path)?.lines().filter_map(IgnoreRule::parse).collect())
+}
+
+impl Ignore {
+    /**
+     * 'load'读取仓库根目录root_path的忽略规则，exclude_path为'.minigit/info/exclude'，各文件夹中的规则在用到时读取
+     */
+    pub fn load(root_path: &Path, exclude_path: &Path) -> Result<Ignore> {
+        Ok(Ignore { root_path: root_path.to_path_buf(), exclude: read_rules(exclude_path)?, cache: RefCell::default() })
+    }
+
+    /**
+     * 'is_ignored'检查仓库中的绝对路径path是否被忽略，is_dir表示它是否为文件夹
+     */
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> Result<bool> {
+        let relative = match path.strip_prefix(&self.root_path) {
+            Ok(relative) => relative,
+            Err(_) => return Ok(false),
+        };
+        let names = relative.iter().map(|name| name.to_string_lossy()).collect::<Vec<_>>();
+        // 依次检查每一层祖先文件夹，祖先被忽略时path也被忽略
+        for depth in 1..=names.len() {
+            let last = depth == names.len();
+            if self.matches(&names[..depth], if last { is_dir } else { true })? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /**
+     * 在exclude和names所有祖先文件夹中的规则里寻找最后一条匹配的规则
+     */
+    fn matches(&self, names: &[Cow<str>], is_dir: bool) -> Result<bool> {
+        let mut ignored = false;
+        let relative = names.join("/");
+        for rule in &self.exclude {
+            if rule.matches(&relative, is_dir) {
+                ignored = !rule.negated;
+            }
+        }
+        let mut dir = self.root_path.clone();
+        for depth in 0..names.len() {
+            if depth > 0 {
+                dir.push(names[depth - 1].as_ref());
+            }
+            let mut cache = self.cache.borrow_mut();
+            if !cache.contains_key(&dir) {
+                cache.insert(dir.clone(), read_rules(&dir.join(IGNORE_FILE))?);
+            }
+            let rules = &cache[&dir];
+            let relative = names[depth..].join("/");
+            for rule in rules {
+                if rule.matches(&relative, is_dir) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        Ok(ignored)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*.o", b"main.o"));
+        assert!(!glob_match(b"*.o", b"dir/main.o"));
+        assert!(glob_match(b"a?c", b"abc"));
+        assert!(glob_match(b"[a-c]x", b"bx") && !glob_match(b"[!a-c]x", b"bx"));
+        assert!(glob_match(b"**/build", b"build") && glob_match(b"**/build", b"a/b/build"));
+        assert!(glob_match(b"doc/**", b"doc/a/b.txt") && !glob_match(b"doc/**", b"src/doc"));
+        assert!(glob_match(b"a/**/z", b"a/z") && glob_match(b"a/**/z", b"a/b/c/z"));
+        assert!(glob_match(b"\\#tag", b"#tag") && glob_match(b"[x", b"[x"));
+    }
+
+    #[test]
+    fn test_ignore() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("minigit-ignore-{}", std::process::id()));
+        fs::create_dir_all(root.join("src").join("gen"))?;
+        fs::write(root.join(IGNORE_FILE), "# build output\ntarget/\n*.swp\n/only_root.txt\n")?;
+        fs::write(root.join("src").join(IGNORE_FILE), "gen/\n!keep.swp\n")?;
+        fs::write(root.join("exclude"), "*.log\n")?;
+        let ignore = Ignore::load(&root, &root.join("exclude"))?;
+        assert!(ignore.is_ignored(&root.join("target"), true)?);
+        assert!(!ignore.is_ignored(&root.join("target"), false)?);
+        assert!(ignore.is_ignored(&root.join("target/debug/main"), false)?);
+        assert!(ignore.is_ignored(&root.join("a.swp"), false)?);
+        assert!(!ignore.is_ignored(&root.join("src/keep.swp"), false)?);
+        assert!(ignore.is_ignored(&root.join("src/gen/a.rs"), false)?);
+        assert!(ignore.is_ignored(&root.join("only_root.txt"), false)?);
+        assert!(!ignore.is_ignored(&root.join("src/only_root.txt"), false)?);
+        assert!(ignore.is_ignored(&root.join("src/debug.log"), false)?);
+        assert!(!ignore.is_ignored(&root.join("src/main.rs"), false)?);
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+}
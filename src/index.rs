@@ -0,0 +1,334 @@
+//! minigit 暂存区：index文件的读写以及文件状态缓存
+
+use std::cmp::Ordering;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::hash::HashAlgorithm;
+use crate::lock::LockFile;
+use crate::object::{bytes_to_hex, bytes_to_os_string, hex_to_bytes, EntryKind};
+
+/**
+ * 文件的状态信息，文件状态没有变化时可以直接使用index中记录的key，不用重新读取和计算
+ * 时间为（秒，纳秒），文件夹的记录不使用状态信息，全部为0
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stat {
+    pub ctime: (i64, u32),
+    pub mtime: (i64, u32),
+    pub ino: u64,
+    pub mode: u32,
+    pub size: u64,
+}
+
+impl Stat {
+    #[cfg(unix)]
+    pub fn from_metadata(metadata: &Metadata) -> Stat {
+        use std::os::unix::fs::MetadataExt;
+        Stat {
+            ctime: (metadata.ctime(), metadata.ctime_nsec() as u32),
+            mtime: (metadata.mtime(), metadata.mtime_nsec() as u32),
+            ino: metadata.ino(),
+            mode: metadata.mode(),
+            size: metadata.size(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_metadata(metadata: &Metadata) -> Stat {
+        let time = |time: std::io::Result<std::time::SystemTime>| {
+            time.ok().and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| (d.as_secs() as i64, d.subsec_nanos()))
+                .unwrap_or_default()
+        };
+        Stat {
+            ctime: time(metadata.created()),
+            mtime: time(metadata.modified()),
+            ino: 0,
+            mode: 0,
+            size: metadata.len(),
+        }
+    }
+}
+
+/**
+ * index文件中的一条记录，path为绝对路径
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    pub key: String,
+    pub stat: Stat,
+}
+
+/**
+ * 暂存区，记录按照路径的字节排序
+ *
+ * index文件的格式为 "MIDX" 加上版本号和记录数量，之后是每一条记录：
+ * ctime、mtime、inode、mode、size、类型、二进制key、路径长度和路径，
+ * 最后是之前全部内容的二进制哈希值，用于检查index文件是否损坏。
+ * 旧版本的文本格式（每行 "路径 类型 key"）仍然可以读取，读取后没有状态信息。
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    entries: Vec<IndexEntry>,
+    // index文件写入的时间，晚于这个时间修改的文件不能使用缓存的key
+    written: Option<(i64, u32)>,
+}
+
+const SIGNATURE: &[u8] = b"MIDX";
+const VERSION: u32 = 1;
+
+/**
+ * 按顺序读取index文件中的各个字段
+ */
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.data.len() < n {
+            return Err(Error::CorruptIndex("unexpected end of index file".to_string()));
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn time(&mut self) -> Result<(i64, u32)> {
+        Ok((self.u64()? as i64, self.u32()?))
+    }
+}
+
+impl Index {
+    /**
+     * 'load'读取path处的index文件，并记录它的修改时间
+     */
+    pub fn load(path: &Path, hash: HashAlgorithm) -> Result<Index> {
+        if !path.is_file() {
+            return Err(Error::CorruptIndex("no such index file".to_string()));
+        }
+        let mut index = Index::parse(hash, &fs::read(path)?)?;
+        index.written = Some(Stat::from_metadata(&fs::metadata(path)?).mtime);
+        Ok(index)
+    }
+
+    /**
+     * 'write'将暂存区写入index文件的锁文件，再重命名为index文件
+     */
+    pub fn write(&self, mut lock: LockFile, hash: HashAlgorithm) -> Result<()> {
+        lock.write_all(&self.serialize(hash))?;
+        lock.commit()
+    }
+
+    pub fn parse(hash: HashAlgorithm, data: &[u8]) -> Result<Index> {
+        if !data.starts_with(SIGNATURE) {
+            return Index::parse_text(data);
+        }
+        let checksum_len = hash.hex_len() / 2;
+        if data.len() < SIGNATURE.len() + checksum_len {
+            return Err(Error::CorruptIndex("index file is too short".to_string()));
+        }
+        let (content, checksum) = data.split_at(data.len() - checksum_len);
+        if hash.hash(content) != bytes_to_hex(checksum) {
+            return Err(Error::CorruptIndex("checksum mismatch".to_string()));
+        }
+        let mut reader = Reader { data: &content[SIGNATURE.len()..] };
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(Error::CorruptIndex(format!("unsupported index version {version}")));
+        }
+        let count = reader.u32()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let stat = Stat {
+                ctime: reader.time()?,
+                mtime: reader.time()?,
+                ino: reader.u64()?,
+                mode: reader.u32()?,
+                size: reader.u64()?,
+            };
+            let kind = match reader.take(1)?[0] {
+                0 => EntryKind::Blob,
+                1 => EntryKind::Tree,
+                2 => EntryKind::Symlink,
+                3 => EntryKind::Executable,
+                other => return Err(Error::CorruptIndex(format!("unknown entry type {other}"))),
+            };
+            let key = bytes_to_hex(reader.take(checksum_len)?);
+            let path_len = reader.u32()? as usize;
+            let path = reader.take(path_len)?;
+            let path = PathBuf::from(bytes_to_os_string(path)
+                .ok_or_else(|| Error::CorruptIndex(format!("invaild path {:?}", String::from_utf8_lossy(path))))?);
+            entries.push(IndexEntry { path, kind, key, stat });
+        }
+        if !reader.data.is_empty() {
+            return Err(Error::CorruptIndex("unexpected data after index entries".to_string()));
+        }
+        Ok(Index { entries, written: None })
+    }
+
+    /**
+     * 读取旧版本的文本格式，每行为 "路径 类型 key"
+     */
+    fn parse_text(data: &[u8]) -> Result<Index> {
+        let mut entries = Vec::new();
+        for line in data.split(|&b| b == b'\n').filter(|line| !line.is_empty()) {
+            let mut it = line.rsplitn(3, |&b| b == b' ');
+            let (key, kind, path) = match (it.next(), it.next(), it.next()) {
+                (Some(key), Some(kind), Some(path)) => (key, kind, path),
+                _ => return Err(Error::CorruptIndex(format!("invaild index entry {:?}", String::from_utf8_lossy(line)))),
+            };
+            let kind = match kind {
+                b"blob" => EntryKind::Blob,
+                b"tree" => EntryKind::Tree,
+                _ => return Err(Error::CorruptIndex(format!("invaild index entry {:?}", String::from_utf8_lossy(line)))),
+            };
+            let path = PathBuf::from(bytes_to_os_string(path)
+                .ok_or_else(|| Error::CorruptIndex(format!("invaild path {:?}", String::from_utf8_lossy(path))))?);
+            entries.push(IndexEntry { path, kind, key: String::from_utf8_lossy(key).into_owned(), stat: Stat::default() });
+        }
+        entries.sort_by(|x, y| x.path.as_os_str().as_encoded_bytes().cmp(y.path.as_os_str().as_encoded_bytes()));
+        Ok(Index { entries, written: None })
+    }
+
+    pub fn serialize(&self, hash: HashAlgorithm) -> Vec<u8> {
+        let mut data = SIGNATURE.to_vec();
+        data.extend_from_slice(&VERSION.to_be_bytes());
+        data.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for entry in &self.entries {
+            let stat = &entry.stat;
+            for (sec, nsec) in [stat.ctime, stat.mtime] {
+                data.extend_from_slice(&(sec as u64).to_be_bytes());
+                data.extend_from_slice(&nsec.to_be_bytes());
+            }
+            data.extend_from_slice(&stat.ino.to_be_bytes());
+            data.extend_from_slice(&stat.mode.to_be_bytes());
+            data.extend_from_slice(&stat.size.to_be_bytes());
+            data.push(match entry.kind {
+                EntryKind::Blob => 0,
+                EntryKind::Tree => 1,
+                EntryKind::Symlink => 2,
+                EntryKind::Executable => 3,
+            });
+            data.append(&mut hex_to_bytes(&entry.key));
+            let path = entry.path.as_os_str().as_encoded_bytes();
+            data.extend_from_slice(&(path.len() as u32).to_be_bytes());
+            data.extend_from_slice(path);
+        }
+        data.append(&mut hex_to_bytes(&hash.hash(&data)));
+        data
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /**
+     * 使用二分查找寻找path，找到时返回Ok(下标)，否则返回Err(应该插入的位置)
+     */
+    fn position(&self, path: &Path) -> std::result::Result<usize, usize> {
+        let path = path.as_os_str().as_encoded_bytes();
+        self.entries.binary_search_by(|entry| entry.path.as_os_str().as_encoded_bytes().cmp(path))
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&IndexEntry> {
+        self.position(path).ok().map(|i| &self.entries[i])
+    }
+
+    /**
+     * 'insert'插入一条记录，如果这个路径已经有记录则更新它
+     */
+    pub fn insert(&mut self, entry: IndexEntry) {
+        match self.position(&entry.path) {
+            Ok(i) => self.entries[i] = entry,
+            Err(i) => self.entries.insert(i, entry),
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<IndexEntry> {
+        self.position(path).ok().map(|i| self.entries.remove(i))
+    }
+
+    /**
+     * 'children'返回文件夹dir中直接包含的文件和文件夹的记录
+     */
+    pub fn children(&self, dir: &Path) -> Vec<&IndexEntry> {
+        let dir_bytes = dir.as_os_str().as_encoded_bytes();
+        let start = match self.position(dir) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+        // 以dir开头的记录是连续的，其中父文件夹为dir的就是直接包含的记录
+        self.entries[start..].iter()
+            .take_while(|entry| entry.path.as_os_str().as_encoded_bytes().starts_with(dir_bytes))
+            .filter(|entry| entry.path.parent() == Some(dir))
+            .collect()
+    }
+
+    /**
+     * 'cached_key'在文件状态与index中的记录相同时返回记录的key
+     * 在index写入之后（或者同一时刻）修改的文件可能与记录的状态相同但内容不同，不能使用缓存
+     * metadata需要由'symlink_metadata'得到，这样符号链接使用的是链接本身的状态
+     */
+    pub fn cached_key(&self, path: &Path, metadata: &Metadata) -> Option<&str> {
+        let entry = self.get(path)?;
+        let stat = Stat::from_metadata(metadata);
+        if entry.kind == EntryKind::Tree || entry.stat == Stat::default() || entry.stat != stat {
+            return None;
+        }
+        match self.written {
+            Some(written) if stat.mtime.cmp(&written) == Ordering::Less => Some(&entry.key),
+            _ => None,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const KEY1: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    fn entry(path: &str, kind: EntryKind) -> IndexEntry {
+        IndexEntry { path: PathBuf::from(path), kind, key: KEY1.to_string(), stat: Stat { size: 3, mtime: (1, 2), ..Default::default() } }
+    }
+
+    #[test]
+    fn test_index() {
+        let mut index = Index::default();
+        for (path, kind) in [("/r/d/b", EntryKind::Blob), ("/r", EntryKind::Tree), ("/r/d.txt", EntryKind::Blob), ("/r/d", EntryKind::Tree)] {
+            index.insert(entry(path, kind));
+        }
+        let paths = |entries: Vec<&IndexEntry>| entries.iter().map(|e| e.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths(index.entries().iter().collect()), vec![Path::new("/r"), Path::new("/r/d"), Path::new("/r/d.txt"), Path::new("/r/d/b")]);
+        assert_eq!(paths(index.children(Path::new("/r"))), vec![Path::new("/r/d"), Path::new("/r/d.txt")]);
+        assert_eq!(paths(index.children(Path::new("/r/d"))), vec![Path::new("/r/d/b")]);
+        let data = index.serialize(HashAlgorithm::Sha1);
+        assert_eq!(Index::parse(HashAlgorithm::Sha1, &data).unwrap().entries(), index.entries());
+        let mut broken = data.clone();
+        broken[20] ^= 1;
+        assert!(matches!(Index::parse(HashAlgorithm::Sha1, &broken), Err(Error::CorruptIndex(_))));
+        index.remove(Path::new("/r/d/b"));
+        assert!(index.children(Path::new("/r/d")).is_empty());
+        // 旧版本的文本格式
+        let text = format!("/r tree {KEY1}\n/r/a blob {KEY1}\n");
+        let old = Index::parse(HashAlgorithm::Sha1, text.as_bytes()).unwrap();
+        assert_eq!(old.get(Path::new("/r/a")).unwrap().stat, Stat::default());
+        assert!(Index::parse(HashAlgorithm::Sha1, b"").unwrap().entries().is_empty());
+    }
+}
@@ -1,14 +1,24 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::ffi::{OsStr, OsString};
-use std::{env,fs, path};
-use std::fs::File;
-use std::error::Error;
-use std::io::{Write, Read};
+use std::env;
+use std::ffi::OsStr;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use crypto::{sha1::Sha1, digest::Digest};
-use flate2::{Compression, read::ZlibEncoder, write::ZlibDecoder};
-use chrono::{DateTime, Utc};
+
+pub mod diff;
+pub mod error;
+pub mod hash;
+pub mod ignore;
+pub mod index;
+pub mod lock;
+pub mod object;
+pub mod reflog;
+pub mod repository;
+pub mod revision;
+use diff::Edit;
+pub use error::{Error, Result};
+pub use hash::HashAlgorithm;
+pub use object::{Blob, Commit, EntryKind, Object, ObjectFormat, ObjectType, Tag, Tree, TreeEntry};
+pub use reflog::ReflogEntry;
+pub use repository::{FileStatus, Head, InitOptions, ListEntry, LogEntry, MergeResult, Repository, ResetMode, Status};
 
 #[derive(Debug)]
 pub struct Config{
@@ -18,28 +28,22 @@ pub struct Config{
 
 impl Config{
     /**
-     *  'build'将minigit指令（通常是命令行输入）装配为Config（配置）
+     *  'build'将minigit指令（通常是命令行输入）装配为Config（配置），第一个参数为程序名字
      * # 示例
      * ```
-        let v = vec!["init","test"];
+        let v = vec!["minigit", "init", "test"];
         let it = v.iter().map(|x|{x.to_string()});
-        let config = Config::build(it).unwrap_or_else(|err| {
-            eprintln!("error at test build: {err}");
-            std::process::exit(1);
-        });
-        assert_eq!((config.operate,config.argument),(String::from("init"),String::from("test")));
+        assert!(minigit::Config::build(it).is_ok());
+        assert!(minigit::Config::build(vec!["minigit".to_string()].into_iter()).is_err());
      * ```
      */
-    pub fn build(mut args: impl Iterator<Item = String>)-> Result<Config, &'static str>{
+    pub fn build(mut args: impl Iterator<Item = String>)-> Result<Config>{
         args.next();
         let operate = match args.next(){
             Some(op) => op,
-            None => return Err("Didn't get operate string in input")
-        };
-        let mut argument = Vec::new();
-        while let Some(arg) = args.next(){
-            argument.push(arg);
+            None => return Err(Error::Usage("Didn't get operate string in input".to_string()))
         };
+        let argument = args.collect();
         Ok(
             Config {
                 operate,
@@ -50,1258 +54,1330 @@ impl Config{
 }
 
 
-
-/** 'run' 通过输入配置，通过运行对应函数来实现对应的minigit指令 \\
- * # 示例
- * ```
-    let config: Config = Config{operate:"init".to_string(), argument:"test".to_string()};
-    run(config);
-    let path =  std::env::current_dir().unwrap_or_else(|err|{
-        eprintln!("test_run failed at get current dir: {err}");
-        std::process::exit(1);
-    });
-    assert!(path.join(config.argument).exists());      
- * ```
+/**
+ * 将命令行中的路径转化为绝对路径，以'*'结尾时指代其所在的文件夹
  */
-pub fn run(config: &Config)-> Result<(), Box<dyn Error>>{
-    match &config.operate as &str{
-        "init" => {
-            if config.argument.is_empty() {
-                return Err("minigit init failed: repository name is empty".into());
-            }
-            init(&config.argument[0])?;
-        },
-        "add" => {
-            add(&config.argument)?;
-            println!("Successed add file: {:?}",&config.argument);
-        },
-        "rm" => {
-            rm(&config.argument)?;
-            println!("Successed remove file: {:?}",&config.argument);
-        },
-        "commit" => {
-            let mut message = &String::new();
-            if !config.argument.is_empty() {
-                message = &config.argument[0];
-            }
-            let author = env::var("USERNAME")?;
-            commit(&author, message)?;
-            println!("Successed commit repository with message: \"{}\"",&config.argument[0]);
-        },
-        "branch" => {
-            let arg = &config.argument;
-            if arg.is_empty() {
-                branch_check()?;
-            }
-            else if arg[0] == "-d".to_string() {
-                if arg.len() < 2 {
-                    return Err("minigit branch failed: branch name is empty".into());
-                }
-                branch_delete(&arg[1])?;
-                println!("Successed delete branch {}",&arg[1]);
-            }
-            else {
-                branch_create(&arg[0])?;
-                println!("Successed create branch {}",&arg[0]);
-            }
-        },
-        "checkout" => {
-            let arg = &config.argument;
-            let n = arg.len();
-            if n == 0 {
-                return Err("minigit checkout failed: branch name is empty".into());
-            }
-            else if n >= 2 && arg[0] == "-b"{
-                checkout_new_branch(&arg[1])?;
-                println!("Switched to branch {}", &arg[1]);
-            }
-            else{
-                checkout(&arg[0])?;
-                println!("Switched to branch {}", &arg[0]);
-            }
-        },
-        "merge" => {
-            if config.argument.is_empty() {
-                return Err("Please input merge branch name".into());
-            }
-            merge(&config.argument[0])?;
-        },
-        _=> return Err("inviald operater string".into()),
+fn command_paths(current_path: &Path, args: &[String])-> Result<Vec<PathBuf>> {
+    let tag = OsStr::new("*");
+    let mut re = Vec::new();
+    for arg in args {
+        let mut path = current_path.join(arg);
+        if path.file_name() == Some(tag) && !path.pop() {
+            return Err(Error::InvalidPath(path));
+        }
+        re.push(path);
     }
-    Ok(())
+    Ok(re)
 }
 
-
-
 /**
- * 'init'根据输入的仓库名字创建minigit仓库，如果该仓库已经存在会将配置初始化，仓库内容不变
- * # 示例
- * ```
- *      let name = "test".to_string();
-        let _unused = init(name).unwrap_or_else(|err|{
-            eprintln!("error at test_init: {err}");
-        });
-        let path = env::current_dir().unwrap();
-        assert!(path.join("test").is_dir());
- * ```
+ * 提交的作者来自环境变量USERNAME
  */
-fn init(name: &String)-> Result<(), Box<dyn Error>>{
-    let path = env::current_dir()?.join(name).join(".minigit");
-    let mut is_first = true;
-    if path.is_dir() {
-        fs::remove_dir_all(&path)?;
-        is_first = false;
-    }
-    fs::create_dir_all(&path)?;
-    fs::create_dir_all(path.join("refs/heads"))?;
-    fs::create_dir(path.join("objects"))?;
-    File::create(path.join("index"))?;
-    let mut head = File::create(path.join("HEAD"))?;
-    head.write_all("master".as_bytes())?;
-    if is_first { 
-        println!("Initialized empty Git repository in {}",path.to_str().unwrap());
-    }
-    else{
-        println!("Reinitialized empty Git repository in {}",path.to_str().unwrap());
-    }
-    Ok(())
+fn author()-> Result<String> {
+    env::var("USERNAME").map_err(|_| Error::Usage("environment variable USERNAME is not set".to_string()))
 }
 
-
-
 /**
- * 'find_minigit'从此路径开始寻找'.minigit'文件夹（也就是minigit库配置文件存放的地方）
+ * 切换分支或者合并会覆盖本地的修改时，列出这些路径
  */
-fn find_minigit(path: &PathBuf)-> Result<PathBuf, Box<dyn Error>>{
-    if !path.exists() {
-        return Err("find minigit faild: invaild path".into());
-    }
-    let mut start_path = path.clone();
-    if start_path.is_file() {
-        if !start_path.pop() {
-            return Err("Can't find minigit because path have no parent".into());
+fn print_overwritten<T>(re: &Result<T>) {
+    if let Err(Error::WouldOverwrite(paths)) = re {
+        for path in paths {
+            println!("Would overwrite: {}", path.display());
         }
     }
-    let target = OsStr::new(".minigit");
-    loop{
-        for entry in start_path.read_dir()? {
-            let child_path = entry?.path();
-            if Some(target) == child_path.file_name() {
-                return Ok(child_path);
-            }
-        }
-        if !start_path.pop() {
-            break;
-        }
-    }
-    return Err("failed find minigit repository".into());
 }
 
+/**
+ * 按照'log'的格式输出一条提交记录
+ */
+fn print_log_entry(entry: &LogEntry) {
+    println!("commit {}", entry.key);
+    if entry.parents.len() > 1 {
+        println!("Merge: {}", entry.parents.iter().map(|p| &p[..7]).collect::<Vec<&str>>().join(" "));
+    }
+    println!("Author: {}", entry.author);
+    println!("Date:   {}", entry.datetime);
+    println!();
+    for line in entry.message.lines() {
+        println!("    {}", line);
+    }
+    println!();
+}
 
 /**
- * 给定一个向量buf，使用二分查找在里面寻找path_str，返回下标（start, end）
- * 如果start <= end 则说明要找的元素在向量里面，且下标为（start + end） / 2
- * 如果start > end 则必定end == start - 1且要找的元素不在向量里面，而且有buf[end] < path_str < buf[start]
+ * 'cat-file -p'输出对象的内容，tree每一项输出一行 "模式 类型 key\t名字"，提交和标签输出各个头部、空行和信息
  */
-fn find_index(buf: &Vec<Vec<u8>>, path_str: &Vec<u8>)-> (usize, i32) {
-    if buf.len() == 0 {
-        return (0,-1);
-    }
-    let mut start: usize = 0;
-    let mut end: usize = buf.len() - 1;
-    while start <= end {
-        let mid: usize = (end + start) / 2;
-        let mid_path = buf[mid].clone();
-        let (mid_path,_) = mid_path.split_at(mid_path.iter().position(|&x| x == b' ').unwrap());
-        let mid_path = mid_path.to_vec();
-        match mid_path.cmp(&path_str) {
-            Ordering::Less=> {
-                start = mid + 1;
+fn print_object(object: &Object)-> Result<()> {
+    match object {
+        Object::Blob(blob)=> std::io::stdout().write_all(&blob.data)?,
+        Object::Tree(tree)=> {
+            for entry in &tree.entries {
+                println!("{:0>6} {} {}\t{}", entry.kind.git_mode(), entry.kind.object_type().as_str(), entry.key, entry.name.to_string_lossy());
             }
-            Ordering::Greater=> {
-                if mid == 0 {
-                    return (0, -1);
-                }
-                end = mid - 1;
-            }
-            Ordering::Equal=> { 
-                break;
+        },
+        Object::Commit(commit)=> {
+            println!("tree {}", commit.tree);
+            for parent in &commit.parents {
+                println!("parent {}", parent);
             }
-        }
+            println!("author {}\ndatetime {}\n\n{}", commit.author, commit.datetime, commit.message);
+        },
+        Object::Tag(tag)=> {
+            println!("object {}\ntype {}\ntag {}", tag.object, tag.object_type.as_str(), tag.name);
+            println!("tagger {}\ndatetime {}\n\n{}", tag.tagger, tag.datetime, tag.message);
+        },
     }
-    (start, end as i32)
+    Ok(())
 }
 
 /**
- * 从路径path开始通过index里面的记录而不是实际文件系统来更新index内容（即buf）
- * 
+ * 'show'输出版本表达式rev指向的对象：提交输出提交记录和相对于父提交的修改，标签输出标签信息和它指向的对象，
+ * tree列出其中的名字（文件夹后面加'/'），blob输出文件内容
  */
-fn updata_index(buf: &mut Vec<Vec<u8>>, path: &PathBuf, root_path: &Path)-> Result<(), Box<dyn Error>> {
-    let path = match path.parent() {
-        None => return Err("updata index file failed: path don't have enough ancestor to minigit path's parent".into()),
-        Some(p)=> p.to_path_buf(),
-    };
-    if !path.starts_with(root_path) {
-        return Ok(());
-    }
-    // 根据index里面的记录而不是实际文件系统来更新path_ancestor
-    let path_str = path.as_os_str().as_encoded_bytes().to_vec();
-    let (start, end) = find_index(buf, &path_str);
-    let mut find_ptr = start;
-    if start as i32 <= end {
-        find_ptr = (start + end as usize) / 2 + 1;
-    }
-    let buf_len = buf.len();
-    let mut value = b"tree\0".to_vec();
-    while find_ptr < buf_len {
-        let find_position = buf[find_ptr].iter().position(|&b| b == b' ').unwrap();
-        let find_path = &buf[find_ptr][0..find_position];
-        if !find_path.starts_with(&path_str) {
-            break;
-        }
-        let last_separator_index = find_path.windows(path::MAIN_SEPARATOR_STR.len()).rposition(|str| str == path::MAIN_SEPARATOR_STR.as_bytes()).unwrap();
-        if find_path[0..last_separator_index] != path_str {
-            find_ptr = find_ptr + 1;
-            continue;
-        }
-        let mut find_key = buf[find_ptr][(find_position + 1)..].to_vec();
-        let mut file_name = find_path[(last_separator_index + 1)..].to_vec();
-        value.append(&mut find_key);
-        value.push(b' ');
-        value.append(&mut file_name);
-        value.append(&mut "\0".as_bytes().to_vec());
-        find_ptr = find_ptr + 1;
-    }
-    let key = save_value(&root_path.join(".minigit"), &value)?;
-    let mut add_information = format!(" tree {key}").into_bytes();
-    let mut path_str = path.as_os_str().as_encoded_bytes().to_vec();
-    path_str.append(&mut add_information);
-    if start as i32 <= end {
-        buf[(start + end as usize) / 2] = path_str;
-    }
-    else {
-        buf.insert(start, path_str);
-    }
-    updata_index(buf, &path, root_path)
-}
-
-
-
-fn start_updata_index(minigit_path: &PathBuf, path: &PathBuf)-> Result<(), Box<dyn Error>> {
-    let root_path = match minigit_path.parent() {
-        None=> return Err("update index file failed: minigit path have no parent".into()),
-        Some(p)=> p,
-    };
-    let index_path = minigit_path.join("index");
-    let mut read = fs::read(&index_path)?;
-    let mut buf = [[].to_vec();0].to_vec();
-    if !read.is_empty() {
-        read.pop();
-        buf = read.split(|&x| x == b'\n').map(|bytes| bytes.to_vec()).collect::<Vec<Vec<u8>>>();
+fn show_object(repository: &Repository, rev: &str)-> Result<()> {
+    match repository.read_object(rev)? {
+        (key, Object::Commit(commit))=> {
+            let entry = LogEntry { key: key.clone(), parents: commit.parents, author: commit.author, datetime: commit.datetime, message: commit.message };
+            print_log_entry(&entry);
+            std::io::stdout().write_all(&repository.diff_commit(&key, 3)?)?;
+        },
+        (_, Object::Tag(tag))=> {
+            println!("tag {}", tag.name);
+            println!("Tagger: {}", tag.tagger);
+            println!("Date:   {}", tag.datetime);
+            println!();
+            println!("{}", tag.message);
+            println!();
+            show_object(repository, &tag.object)?;
+        },
+        (_, Object::Tree(tree))=> {
+            println!("tree {}", rev);
+            println!();
+            for entry in &tree.entries {
+                let slash = if entry.kind == EntryKind::Tree { "/" } else { "" };
+                println!("{}{}", entry.name.to_string_lossy(), slash);
+            }
+        },
+        (_, object)=> print_object(&object)?,
     }
-    // 接下来应该更新此路径上全部的key
-    updata_index(&mut buf, path, root_path)?;
-    // 最后将index清空后将buf写入index文件
-    let buf: Vec<u8> = buf.iter().flat_map(|v| {let mut w = v.clone(); w.push(b'\n'); w}).collect();
-    let mut index = File::create(&index_path)?;
-    index.write_all(&buf)?;
     Ok(())
 }
 
-
-fn insert_index(minigit_path: &PathBuf, path: &PathBuf,key: &String)-> Result<(),Box<dyn Error>> {
-    let mut path_type = "tree";
-    if path.is_file() {
-        path_type = "blob";
-    }
-    let mut add_information = format!(" {path_type} {key}").into_bytes();
-    let mut path_str = path.as_os_str().as_encoded_bytes().to_vec();
-    let index_path = minigit_path.join("index");
-    let mut index = File::open(&index_path)?;
-    let mut read = Vec::new();
-    index.read_to_end(&mut read)?;
-    let mut buf;
-    if !read.is_empty() {
-        read.pop();
-        buf = read.split(|&x| x == b'\n').map(|bytes| bytes.to_vec()).collect::<Vec<Vec<u8>>>();
-        let (start, end) = find_index(&buf, &path_str);
-        if start as i32 > end {
-            path_str.append(&mut add_information);
-            buf.insert(start, path_str);
-        }
-        else { // start <= end 说明此时buf[(start + end) / 2]的路径与path_str一样，该更新而不是插入
-            path_str.append(&mut add_information);
-            let mid = (start + end as usize) / 2;
-            buf[mid] = path_str;
-        }
-    }
-    else {
-        path_str.append(&mut add_information);
-        buf = [path_str;1].to_vec();
-    }
-    let buf: Vec<u8> = buf.iter().flat_map(|v| {let mut w = v.clone(); w.push(b'\n'); w}).collect();
-    let mut index = File::create(&index_path)?;
-    index.write_all(&buf)?;
+/**
+ * 'ls-files'和'ls-tree'输出的一行，使用'-z'时以'\0'结尾，否则以换行结尾
+ */
+fn print_line(line: &str, nul: bool)-> Result<()> {
+    let end = if nul { "\0" } else { "\n" };
+    std::io::stdout().write_all(format!("{line}{end}").as_bytes())?;
     Ok(())
 }
 
-
-
-
-
-fn save_value(minigit_path: &PathBuf,value: &Vec<u8>)-> Result<String, Box<dyn Error>> {
-    if !minigit_path.is_dir() {
-        return Err(".minigit doesn't exists".into());
-    }
-    // 将value中的数据使用SHA1算法加密成key
-    let mut hasher = Sha1::new();
-    hasher.input(value);
-    let key: &str = &hasher.result_str();
-    let save_path = minigit_path.join("objects").join(&key[0..2]);
-    if !save_path.is_dir(){
-        fs::create_dir(&save_path)?;
-    }
-    let save_path = save_path.join(&key[2..]);
-    if !save_path.is_file(){ 
-        let mut save_file = File::create(save_path)?;
-        save_file.write_all(value)?;
-    }
-    Ok(String::from(key))
-}
-
-fn save_blob(path: &PathBuf, minigit_path: &PathBuf)-> Result<String, Box<dyn Error>> {
-    let file = File::open(path)?;
-    // 将path代表的文件的二进制内容使用zlib压缩并且存入字符动态数组value中
-    let mut value = Vec::new();
-    value.append(&mut "blob\0".as_bytes().to_vec());
-    let mut zlib = ZlibEncoder::new(file, Compression::fast());
-    zlib.read_to_end(&mut value)?;
-    let key = save_value(minigit_path, &value)?;
-    insert_index(minigit_path, path, &key)?;
-    Ok(key)
-}
-
-fn save_tree(path: &PathBuf, minigit_path: &PathBuf)-> Result<String, Box<dyn Error>> {
-    let mut value = Vec::new();
-    value.append(&mut "tree\0".as_bytes().to_vec());
-    for entry in path.read_dir()? {
-        let key: String;
-        let child_type;
-        let child_path = entry?.path();
-        let child_name = child_path.file_name();
-        let child_name = match child_name {
-            None=> return Err(r"save_tree failed: child_path can't end with \..".into()),
-            Some(name)=> name,
-        };
-        if child_path.is_file() {
-            child_type = "blob";
-            key = save_blob(&child_path, minigit_path)?;
-        }
-        else {
-            child_type = "tree";
-            key = save_tree(&child_path, minigit_path)?;
-        }
-        value.append(&mut format!("{child_type} {key} ").into_bytes());
-        value.append(&mut child_name.as_encoded_bytes().to_vec());
-        value.append(&mut "\0".as_bytes().to_vec());
+fn parse_number(arg: &str)-> Result<usize> {
+    // usize的parse接受前导的'+'，这里只接受数字
+    if arg.is_empty() || !arg.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Error::Usage(format!("invaild number {arg:?}")));
     }
-    let dir_key = save_value(minigit_path, &value)?;
-    insert_index(minigit_path,path,&dir_key)?;
-    Ok(dir_key)
+    arg.parse().map_err(|_| Error::Usage(format!("invaild number {arg:?}")))
 }
 
-
-fn save_object(path: &PathBuf)-> Result<(), Box<dyn Error>> {
-    let minigit_path = &find_minigit(&path)?;
-    if path == minigit_path {
-        return Ok(());
-    }
-    if path.starts_with(minigit_path) {
-        return Err("save_object failed: can't save sub path of minigit path".into());
-    }
-    if path.is_file() {
-        save_blob(path, minigit_path)?;
+/**
+ * 'split_revisions'把diff的参数分为版本表达式和路径（路径相对于current_path）
+ * 与git相同，"--"之前的都是版本表达式；没有"--"时开头能够解析为版本表达式的参数是版本表达式，从第一个不能解析的参数开始都是路径，
+ * 既是版本表达式又是存在的路径，或者既不是版本表达式又不是存在的路径的参数需要用"--"区分
+ */
+fn split_revisions<'a>(repository: &Repository, current_path: &Path, args: &'a [&'a str])-> Result<(&'a [&'a str], &'a [&'a str])> {
+    if let Some(i) = args.iter().position(|arg| *arg == "--") {
+        return Ok((&args[..i], &args[(i + 1)..]));
     }
-    else if path.is_dir() {
-        let repository_path = match minigit_path.parent() {
-            None => return Err("save_object failed: can't get repository path from minigit path".into()), 
-            Some(father)=> father,
-        };
-        if path == repository_path {
-            let ignore = OsStr::new(".minigit");
-            for entry in path.read_dir()? {
-                let entry = entry?;
-                if entry.file_name() != ignore {
-                    let file_type = entry.file_type()?;
-                    if file_type.is_dir() {
-                        save_tree(&entry.path(), minigit_path)?;
-                    }
-                    else if file_type.is_file() {
-                        save_blob(&entry.path(), minigit_path)?;
-                    }
-                    else {
-                        return Err("save object failed: can't save symlink file".into());
-                    }
-                    
-                }
-            }
-            return start_updata_index(minigit_path, minigit_path);
-        }
-        else {
-            save_tree(path, minigit_path)?;
-        }
+    let (revs, paths) = args.split_at(args.iter().take_while(|arg| repository.resolve_revision(arg).is_ok()).count());
+    if let Some(arg) = revs.iter().find(|arg| current_path.join(arg).exists()) {
+        return Err(Error::Usage(format!("ambiguous argument {arg}: both revision and path, use '--' to separate them")));
     }
-    else {
-        return Err("save_object failed: Invaid path".into())
+    if let Some(arg) = paths.iter().find(|arg| !current_path.join(arg).exists()) {
+        return Err(Error::Usage(format!("ambiguous argument {arg}: unknown revision or path, use '--' to separate them")));
     }
-    start_updata_index(minigit_path,path)
+    Ok((revs, paths))
 }
 
-
-/**
- * add 函数负责将一系列文件或者文件夹保存到索引，如果已经保存则检查是否有改变，如果有改变则保存改变后的新文件到索引
+/** 'run' 通过输入配置，通过运行对应函数来实现对应的minigit指令 \\
+ * 除了'init'以外，所有操作都从当前工作目录开始向上寻找仓库
+ * # 示例
+ * ```
+    let path = std::env::temp_dir().join("minigit-doc-run");
+    let args = vec!["minigit".to_string(), "init".to_string(), path.display().to_string()];
+    let config = minigit::Config::build(args.into_iter()).unwrap();
+    minigit::run(&config).unwrap();
+    assert!(path.join(".minigit").is_dir());
+ * ```
  */
-fn add(paths: &Vec<String>)-> Result<(), Box<dyn Error>> {
+pub fn run(config: &Config)-> Result<()>{
     let current_path = env::current_dir()?;
-    let paths = paths.iter().map(|str| {
-                                    let mut cstr = str.clone();
-                                    if  cstr.ends_with('.') {
-                                        cstr.pop();
-                                        cstr.push('*');
-                                    }
-                                    current_path.join(cstr)})
-                                    .collect::<Vec<PathBuf>>();
-    let tag = OsStr::new("*");
-    for path in paths {
-        let file_name = match path.file_name() {
-            None=> return Err(r"add failed: path can't end with \..".into()),
-            Some(name)=> name,
-        };
-        if file_name == tag{
-            let mut save_path = path.clone();
-            if !save_path.pop() {
-                return Err(r"add failed: path have no parent and end with \. or \*".into());
+    if config.operate == "init" {
+        let mut options = InitOptions::default();
+        let mut name = None;
+        for arg in &config.argument {
+            if let Some(format) = arg.strip_prefix("--format=") {
+                options.object_format = ObjectFormat::from_name(format)
+                                        .ok_or_else(|| Error::Usage(format!("minigit init failed: unknown object format {format}")))?;
             }
-            for entry in save_path.read_dir()? {
-                save_object(&entry?.path())?;
+            else if let Some(hash) = arg.strip_prefix("--object-format=") {
+                options.hash_algorithm = HashAlgorithm::from_name(hash)
+                                         .ok_or_else(|| Error::Usage(format!("minigit init failed: unknown hash algorithm {hash}")))?;
+            }
+            else {
+                name = Some(arg);
             }
         }
-        else {
-            save_object(&path)?;
-        }
-    }
-    Ok(())
-}
-
-
-
-
-fn remove_index(minigit_path: &PathBuf, path: &PathBuf)-> Result<(), Box<dyn Error>> {
-    let path_str = path.as_os_str().as_encoded_bytes().to_vec();
-    let index_path = minigit_path.join("index");
-    let mut index = File::open(&index_path)?;
-    let mut buf = Vec::new();
-    index.read_to_end(&mut buf)?;
-    if buf.len() > 0 {
-        buf.pop();
-    }
-    let mut buf = buf.split(|&x| x == b'\n').map(|bytes| bytes.to_vec()).collect::<Vec<Vec<u8>>>();
-    let (start, end) = find_index(&buf, &path_str);
-    if start as i32 <= end {
-        buf.remove((start + end as usize) / 2);
-    }
-    // 接下来应该更新此路径上全部的key
-    let root_path = match minigit_path.parent() {
-        None=> return Err("update index file failed: minigit path have no parent".into()),
-        Some(p)=> p,
-    };
-    updata_index(&mut buf, path, root_path)?;
-    // 最后将index清空后将buf写入index文件
-    let buf: Vec<u8> = buf.iter().flat_map(|v| {let mut w = v.clone(); w.push(b'\n'); w}).collect();
-    let mut index = File::create(&index_path)?;
-    index.write_all(&buf)?;
-    Ok(())
-}
-
-fn remove_blob(minigit_path: &PathBuf, path: &PathBuf)-> Result<(), Box<dyn Error>> {
-    fs::remove_file(path)?;
-    remove_index(minigit_path, path)?;
-    Ok(())
-}
-
-fn remove_tree(minigit_path: &PathBuf, path: &PathBuf)-> Result<(), Box<dyn Error>> {
-    for entry in path.read_dir()? {
-        let child_path = &entry?.path();
-        if child_path.is_file() {
-            remove_blob(minigit_path, child_path)?;
+        let name = name.ok_or_else(|| Error::Usage("minigit init failed: repository name is empty".to_string()))?;
+        let path = current_path.join(name);
+        let is_first = !path.join(".minigit").is_dir();
+        let repository = Repository::init_with_options(&path, &options)?;
+        if is_first {
+            println!("Initialized empty Git repository in {}",repository.minigit_path().display());
         }
-        else {
-            remove_tree(minigit_path, child_path)?;
+        else{
+            println!("Reinitialized existing Git repository in {}",repository.minigit_path().display());
         }
-    }
-    fs::remove_dir(path)?;
-    remove_index(minigit_path, path)?;
-    Ok(())
-}
-
-
-fn remove_object(path: &PathBuf)-> Result<(), Box<dyn Error>> {
-    let minigit_path = &find_minigit(&path)?;
-    if path == minigit_path {
         return Ok(());
     }
-    if path.starts_with(minigit_path) {
-        return Err("remove_object failed: can't delete sub path of minigit path".into());
-    }
-    if path.is_file() {
-        remove_blob(minigit_path, path)?;
+    let mut repository = Repository::discover(&current_path)?;
+    if let Ok(identity) = author() {
+        repository.set_identity(&identity);
     }
-    else if path.is_dir() {
-        let repository_path = match minigit_path.parent() {
-            None => return Err("remove_object failed: can't get repository path from minigit path".into()), 
-            Some(father)=> father,
-        };
-        if path == repository_path {
-            let ignore = OsStr::new(".minigit");
-            for entry in path.read_dir()? {
-                let entry = entry?;
-                if entry.file_name() != ignore {
-                    let file_type = entry.file_type()?;
-                    if file_type.is_dir() {
-                        remove_tree(minigit_path, &entry.path())?;
+    match &config.operate as &str{
+        "add" => {
+            repository.add(&command_paths(&current_path, &config.argument)?)?;
+            println!("Successed add file: {:?}",&config.argument);
+        },
+        "rm" => {
+            let arg = &config.argument;
+            if arg.first().is_some_and(|arg| arg == "--cached") {
+                repository.rm_cached(&command_paths(&current_path, &arg[1..])?)?;
+                println!("Successed remove file from index: {:?}",&arg[1..]);
+            }
+            else {
+                repository.rm(&command_paths(&current_path, arg)?)?;
+                println!("Successed remove file: {:?}",arg);
+            }
+        },
+        "commit" => {
+            let message = config.argument.first().map(String::as_str).unwrap_or("");
+            let author = author()?;
+            repository.commit(&author, message)?;
+            println!("Successed commit repository with message: \"{}\"",message);
+        },
+        "branch" => {
+            let arg = &config.argument;
+            if arg.is_empty() {
+                let head = repository.head()?;
+                if let Head::Detached(key) = &head {
+                    println!("* (HEAD detached at {})", &key[..7]);
+                }
+                let now_branch_name = head.name();
+                for branch_name in repository.branches()? {
+                    if branch_name == now_branch_name {
+                        println!("* {}",branch_name);
                     }
-                    else if file_type.is_file() {
-                        remove_blob(minigit_path, &entry.path())?;
+                    else{
+                        println!(" {}",branch_name);
                     }
-                    else {
-                        return Err("remove object failed: can't delete symlink file".into());
+                }
+            }
+            else if arg[0] == "-d" {
+                if arg.len() < 2 {
+                    return Err(Error::Usage("minigit branch failed: branch name is empty".to_string()));
+                }
+                repository.delete_branch(&arg[1])?;
+                println!("Successed delete branch {}",&arg[1]);
+            }
+            else {
+                match arg.get(1) {
+                    Some(start)=> repository.create_branch_at(&arg[0], start)?,
+                    None=> repository.create_branch(&arg[0])?,
+                }
+                println!("Successed create branch {}",&arg[0]);
+            }
+        },
+        "tag" => {
+            let mut annotate = false;
+            let mut delete = false;
+            let mut list = false;
+            let mut message = None;
+            let mut names = Vec::new();
+            let mut it = config.argument.iter();
+            while let Some(arg) = it.next() {
+                match arg.as_str() {
+                    "-a"=> annotate = true,
+                    "-d"=> delete = true,
+                    "-l" | "--list"=> list = true,
+                    "-m"=> match it.next() {
+                        None=> return Err(Error::Usage("minigit tag failed: -m need a message".to_string())),
+                        Some(text)=> message = Some(text.as_str()),
+                    },
+                    _=> names.push(arg.as_str()),
+                }
+            }
+            if delete {
+                if names.is_empty() {
+                    return Err(Error::Usage("minigit tag failed: tag name is empty".to_string()));
+                }
+                for name in names {
+                    let key = repository.delete_tag(name)?;
+                    println!("Deleted tag {} (was {})", name, &key[..7]);
+                }
+            }
+            else if list || names.is_empty() {
+                // "tag -l 通配符" 只列出名字匹配的标签
+                let pattern = names.first();
+                for name in repository.tags()? {
+                    if pattern.is_none_or(|pattern| ignore::glob_match(pattern.as_bytes(), name.as_bytes())) {
+                        println!("{}", name);
                     }
-                    
                 }
             }
-        }
-        else {
-            remove_tree(minigit_path, path)?;
-        }
-    }
-    else {
-        return Err("remove_object failed: Invaid path".into())
-    }
-    start_updata_index(minigit_path,path)
-}
-
-
-fn rm(paths: &Vec<String>)->Result<(), Box<dyn Error>> {
-    let current_path = env::current_dir()?;
-    let paths = paths.iter().map(|str| {
-                                    let mut cstr = str.clone();
-                                    if  cstr.ends_with('.') {
-                                        cstr.pop();
-                                        cstr.push('*');
-                                    }
-                                    current_path.join(cstr)})
-                                    .collect::<Vec<PathBuf>>();
-    let tag = OsStr::new("*");
-    for path in paths {
-        let file_name = match path.file_name() {
-            None=> return Err(r"add failed: path can't end with \..".into()),
-            Some(name)=> name,
-        };
-        if file_name == tag{
-            let mut remove_path = path.clone();
-            if !remove_path.pop() {
-                return Err(r"add failed: path have no parent and end with \. or \*".into());
+            else {
+                let target = names.get(1).copied();
+                // 与git相同，使用-m时总是创建附注标签
+                if annotate || message.is_some() {
+                    let message = message.ok_or_else(|| Error::Usage("minigit tag failed: annotated tag need a message, use -m".to_string()))?;
+                    repository.create_annotated_tag(names[0], target, &author()?, message)?;
+                }
+                else {
+                    repository.create_tag(names[0], target)?;
+                }
+                println!("Successed create tag {}", names[0]);
+            }
+        },
+        "checkout" => {
+            let force = config.argument.iter().any(|arg| arg == "-f" || arg == "--force");
+            let arg = config.argument.iter().filter(|arg| *arg != "-f" && *arg != "--force").collect::<Vec<_>>();
+            let n = arg.len();
+            if n == 0 {
+                return Err(Error::Usage("minigit checkout failed: branch name is empty".to_string()));
             }
-            for entry in remove_path.read_dir()? {
-                remove_object(&entry?.path())?;
+            else if n >= 2 && arg[0] == "-b"{
+                match arg.get(2) {
+                    // 在其他提交上创建新分支时需要重建工作区
+                    Some(start)=> {
+                        repository.create_branch_at(arg[1], start)?;
+                        let re = if force { repository.force_checkout(arg[1]) } else { repository.checkout(arg[1]) };
+                        print_overwritten(&re);
+                        re?;
+                    },
+                    None=> repository.checkout_new_branch(arg[1])?,
+                }
+                println!("Switched to branch {}", arg[1]);
             }
-        }
-        else {
-            remove_object(&path)?;
-        }
+            else{
+                let re = if force { repository.force_checkout(arg[0]) } else { repository.checkout(arg[0]) };
+                print_overwritten(&re);
+                re?;
+                match repository.head()? {
+                    Head::Branch(name)=> println!("Switched to branch {}", name),
+                    Head::Detached(key)=> println!("HEAD is now at {}, you are in 'detached HEAD' state", &key[..7]),
+                }
+            }
+        },
+        "reset" => {
+            let mut mode = None;
+            let mut commit = None;
+            let mut it = config.argument.iter();
+            for arg in it.by_ref() {
+                match arg.as_str() {
+                    "--soft"=> mode = Some(ResetMode::Soft),
+                    "--mixed"=> mode = Some(ResetMode::Mixed),
+                    "--hard"=> mode = Some(ResetMode::Hard),
+                    "--"=> break,
+                    _ if commit.is_none()=> commit = Some(arg.as_str()),
+                    _=> return Err(Error::Usage(format!("minigit reset failed: unexpected argument {arg}, use \"--\" before paths"))),
+                }
+            }
+            let paths = it.cloned().collect::<Vec<String>>();
+            if !paths.is_empty() {
+                if mode.is_some_and(|mode| mode != ResetMode::Mixed) {
+                    return Err(Error::Usage("minigit reset failed: --soft and --hard can't be used with paths".to_string()));
+                }
+                repository.reset_paths(commit, &command_paths(&current_path, &paths)?)?;
+                println!("Successed reset file: {:?}", paths);
+            }
+            else {
+                let key = repository.reset(commit, mode.unwrap_or_default())?;
+                println!("HEAD is now at {}", &key[..7]);
+            }
+        },
+        "restore" => {
+            let mut source = None;
+            let mut staged = false;
+            let mut paths = Vec::new();
+            let mut it = config.argument.iter();
+            while let Some(arg) = it.next() {
+                if arg == "--staged" {
+                    staged = true;
+                }
+                else if arg == "--source" {
+                    match it.next() {
+                        None=> return Err(Error::Usage("minigit restore failed: --source need a commit".to_string())),
+                        Some(rev)=> source = Some(rev.as_str()),
+                    }
+                }
+                else if let Some(rev) = arg.strip_prefix("--source=") {
+                    source = Some(rev);
+                }
+                else if arg != "--" {
+                    paths.push(arg.clone());
+                }
+            }
+            if paths.is_empty() {
+                return Err(Error::Usage("minigit restore failed: path is empty".to_string()));
+            }
+            repository.restore(source, staged, &command_paths(&current_path, &paths)?)?;
+            println!("Successed restore file: {:?}", paths);
+        },
+        "diff" => {
+            let mut context = 3;
+            let mut args = Vec::new();
+            let mut separated = false;
+            for arg in &config.argument {
+                if separated {
+                    args.push(arg.as_str());
+                }
+                else if let Some(n) = arg.strip_prefix("-U").or(arg.strip_prefix("--unified=")) {
+                    context = parse_number(n)?;
+                }
+                else if arg == "--" {
+                    separated = true;
+                    args.push(arg.as_str());
+                }
+                else {
+                    args.push(arg.as_str());
+                }
+            }
+            let (revs, paths) = split_revisions(&repository, &current_path, &args)?;
+            let paths = paths.iter().map(|arg| current_path.join(arg)).collect::<Vec<_>>();
+            let out = match revs {
+                []=> repository.diff_worktree(&paths, context)?,
+                [rev]=> repository.diff_revision_worktree(rev, &paths, context)?,
+                [rev1, rev2]=> repository.diff_revisions(rev1, rev2, &paths, context)?,
+                _=> return Err(Error::Usage("minigit diff failed: too many revisions to compare".to_string())),
+            };
+            std::io::stdout().write_all(&out)?;
+        },
+        "status" => {
+            let re = repository.status()?;
+            if re.detached {
+                println!("HEAD detached at {}", &re.branch[..7]);
+            }
+            else {
+                println!("On branch {}", re.branch);
+            }
+            let describe = |s: &FileStatus| match s {
+                FileStatus::New=> "new file:   ",
+                FileStatus::Modified=> "modified:   ",
+                FileStatus::Deleted=> "deleted:    ",
+            };
+            if !re.staged.is_empty() {
+                println!("Changes to be committed:");
+                for (name, s) in &re.staged {
+                    println!("\t{}{}", describe(s), name);
+                }
+            }
+            if !re.unmerged.is_empty() {
+                println!("Unmerged paths:");
+                println!("  (use \"minigit add <file>...\" to mark resolution)");
+                for name in &re.unmerged {
+                    println!("\tunmerged:   {}", name);
+                }
+            }
+            if !re.unstaged.is_empty() {
+                println!("Changes not staged for commit:");
+                for (name, s) in &re.unstaged {
+                    println!("\t{}{}", describe(s), name);
+                }
+            }
+            if !re.untracked.is_empty() {
+                println!("Untracked files:");
+                for name in &re.untracked {
+                    println!("\t{}", name);
+                }
+            }
+            if re.staged.is_empty() && re.unstaged.is_empty() && re.unmerged.is_empty() && re.untracked.is_empty() {
+                println!("nothing to commit, working tree clean");
+            }
+        },
+        "log" => {
+            let mut limit = None;
+            let mut oneline = false;
+            let mut branch = None;
+            let mut it = config.argument.iter();
+            while let Some(arg) = it.next() {
+                if arg == "--oneline" {
+                    oneline = true;
+                }
+                else if arg == "-n" {
+                    match it.next() {
+                        None=> return Err(Error::Usage("minigit log failed: -n need a number".to_string())),
+                        Some(n)=> limit = Some(parse_number(n)?),
+                    }
+                }
+                else if let Some(n) = arg.strip_prefix("-n").or(arg.strip_prefix("-")) {
+                    limit = Some(parse_number(n)?);
+                }
+                else {
+                    branch = Some(arg.as_str());
+                }
+            }
+            for entry in repository.log(branch, limit)? {
+                if oneline {
+                    println!("{} {}", &entry.key[..7], entry.message.lines().next().unwrap_or(""));
+                }
+                else {
+                    print_log_entry(&entry);
+                }
+            }
+        },
+        "cat-file" => {
+            let (option, rev) = match &config.argument[..] {
+                [option, rev] if ["-t", "-s", "-p"].contains(&option.as_str())=> (option.as_str(), rev),
+                _=> return Err(Error::Usage("Please input cat-file -t, -s or -p and an object".to_string())),
+            };
+            match option {
+                "-t"=> println!("{}", repository.read_object(rev)?.1.object_type().as_str()),
+                "-s"=> println!("{}", repository.object_size(rev)?),
+                _=> print_object(&repository.read_object(rev)?.1)?,
+            }
+        },
+        "show" => {
+            let rev = config.argument.first().map(String::as_str).unwrap_or("HEAD");
+            show_object(&repository, rev)?;
+        },
+        "ls-files" => {
+            let mut stage = false;
+            let mut nul = false;
+            let mut paths = Vec::new();
+            for arg in &config.argument {
+                match arg.as_str() {
+                    "-s" | "--stage"=> stage = true,
+                    "-z"=> nul = true,
+                    _=> paths.push(current_path.join(arg)),
+                }
+            }
+            for entry in repository.ls_files(&paths)? {
+                // 暂存区中没有冲突的记录，stage总是0
+                let line = if stage { format!("{} {} 0\t{}", entry.kind.git_mode(), entry.key, entry.path) } else { entry.path };
+                print_line(&line, nul)?;
+            }
+        },
+        "ls-tree" => {
+            let mut recursive = false;
+            let mut name_only = false;
+            let mut nul = false;
+            let mut args = Vec::new();
+            for arg in &config.argument {
+                match arg.as_str() {
+                    "-r"=> recursive = true,
+                    "--name-only"=> name_only = true,
+                    "-z"=> nul = true,
+                    _=> args.push(arg.as_str()),
+                }
+            }
+            let (rev, path) = match args[..] {
+                [rev]=> (rev, None),
+                [rev, path]=> (rev, Some(current_path.join(path))),
+                _=> return Err(Error::Usage("Please input ls-tree [-r] [-z] [--name-only] commit [path]".to_string())),
+            };
+            for entry in repository.ls_tree(rev, path.as_deref(), recursive)? {
+                let line = if name_only {
+                    entry.path
+                }
+                else {
+                    format!("{:0>6} {} {}\t{}", entry.kind.git_mode(), entry.kind.object_type().as_str(), entry.key, entry.path)
+                };
+                print_line(&line, nul)?;
+            }
+        },
+        "reflog" => {
+            let branch = config.argument.first().map(String::as_str);
+            for (i, entry) in repository.reflog(branch)?.iter().enumerate() {
+                println!("{} {}@{{{}}}: {}", &entry.new_key[..7], branch.unwrap_or("HEAD"), i, entry.message);
+            }
+        },
+        "merge" => {
+            let force = config.argument.iter().any(|arg| arg == "-f" || arg == "--force");
+            let branch_name = match config.argument.iter().find(|arg| *arg != "-f" && *arg != "--force") {
+                None=> return Err(Error::Usage("Please input merge branch name".to_string())),
+                Some(name)=> name,
+            };
+            let author = author()?;
+            let re = if force { repository.force_merge(branch_name, &author) } else { repository.merge(branch_name, &author) };
+            print_overwritten(&re);
+            match re? {
+                MergeResult::UpToDate=> println!("Already up to date."),
+                MergeResult::FastForward(key)=> println!("Fast-forward to {key}"),
+                MergeResult::Merged(key)=> println!("Merge made commit {key}"),
+                MergeResult::Conflict(paths)=> {
+                    for path in &paths {
+                        println!("Conflict at: {}", path.display());
+                    }
+                    return Err(Error::MergeConflict(paths));
+                },
+            }
+        },
+        _=> return Err(Error::Usage(format!("inviald operater string {}", config.operate))),
     }
     Ok(())
 }
 
 
 
-
-fn create_tree_from_index(minigit_path: &PathBuf)-> Result<Vec<u8>, Box<dyn Error>> {
-    let index_path = minigit_path.join("index");
-    if !index_path.is_file() {
-        return Err("commit failed: no such index file".into());
-    }
-    let mut index = File::open(index_path)?;
-    let mut buf = [0;256];
-    let n = index.read(&mut buf)?;
-    if n == 0 {
-        return Ok("\0".as_bytes().to_vec());
-    }
-    let start = buf.iter().position(|&b| b == b' ').unwrap() + 6;
-    let end = buf.iter().position(|&b| b == b'\n').unwrap();
-    let key = buf[start..end].to_vec();
-    Ok(key)
+/**
+ * 'get_diff_from_vec'使用Myers算法比较两个向量，返回将v1变为v2的最短编辑脚本
+ */
+pub fn get_diff_from_vec<T: PartialEq>(v1: &[T], v2: &[T])-> Vec<Edit> {
+    diff::myers(v1, v2)
 }
 
 
-fn commit(author: &String, message: &String)-> Result<(), Box<dyn Error>> {
-    let minigit_path = &find_minigit(& env::current_dir()?)?;
-    let mut tree_key = create_tree_from_index(minigit_path)?;
-    let mut head = File::open(minigit_path.join("HEAD"))?;
-    let mut current_commit = String::new();
-    head.read_to_string(&mut current_commit)?;
-    let current_commit = minigit_path.join(minigit_path.join("refs").join("heads").join(&current_commit));
-    let mut parent_commit = String::new();
-    if !current_commit.is_file() {
-        parent_commit = "\0".to_string();
-    }
-    else {
-        let mut head = File::open(&current_commit)?;
-        head.read_to_string(&mut parent_commit)?;
-    }
-    let now: DateTime<Utc> = Utc::now();
-    let mut commit_value = format!("commit\0parent {parent_commit}\nauthor {author}\ndatetime {now}\nnote {message}\ntree ").into_bytes();
-    commit_value.append(&mut tree_key);
-    let key = save_value(minigit_path, &commit_value)?;
-    let mut head = File::create(&current_commit)?;
-    head.write_all(&key.into_bytes())?;
-    Ok(())
-}
 
 
-fn branch_create(name: &String)-> Result<(), Box<dyn Error>> {
-    let minigit_path = find_minigit(&env::current_dir()?)?;
-    let branch_path = minigit_path.join("refs").join("heads").join(name);
-    if branch_path.is_file() {
-        return Err("create branch failed: branch {name} is existing, you can't create a existing branch".into());
-    }
-    let now_branch_name = fs::read_to_string(minigit_path.join("HEAD"))?;
-    let last_commit_key = fs::read(minigit_path.join("refs").join("heads").join(&now_branch_name))?;
-    fs::write(branch_path, last_commit_key)?;
-    Ok(())
-}
+#[cfg(test)]
+mod test{
 
-fn branch_check()-> Result<(), Box<dyn Error>> {
-    let minigit_path = find_minigit(&env::current_dir()?)?;
-    let branchs_path = minigit_path.join("refs").join("heads");
-    let now_branch_name = fs::read_to_string(minigit_path.join("HEAD"))?;
-    for entry in branchs_path.read_dir()? {
-        let branch_name = entry?.file_name().into_string().unwrap();
-        if branch_name == now_branch_name {
-            println!("* {}",branch_name);
-        }
-        else{
-            println!(" {}",branch_name);
+    use super::*;
+    use std::fs;
+
+    /**
+     * 在系统临时文件夹中创建一个新的测试仓库，不同测试使用不同名字，互不影响
+     */
+    fn test_repository(name: &str)-> Result<Repository> {
+        let path = env::temp_dir().join(format!("minigit-{name}-{}", std::process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
         }
+        fs::create_dir_all(&path)?;
+        Repository::init(&path)
     }
-    Ok(())
-}
 
-fn branch_delete(name: &String)-> Result<(), Box<dyn Error>> {
-    let minigit_path = find_minigit(&env::current_dir()?)?;
-    let branch_path = minigit_path.join("refs").join("heads").join(name);
-    let now_branch_name = fs::read_to_string(minigit_path.join("HEAD"))?;
-    if &now_branch_name == name {
-        return Err("delete branch failed: can't delete now branch".into());
-    }
-    if branch_path.is_file() {
-        fs::remove_file(branch_path)?;
-        return Ok(())
-    }
-    else {
-        return Err("delete branch failed: no such branch".into());
+    #[test]
+    fn test_build(){
+        let v = ["minigit","init","test"];
+        let it = v.iter().map(|x|{x.to_string()});
+        let config = Config::build(it).unwrap();
+        assert_eq!((config.operate,config.argument),(String::from("init"),vec![String::from("test")]));
+        assert!(Config::build(vec!["minigit".to_string()].into_iter()).is_err());
     }
-}
-
-fn checkout_new_branch(branch_name: &String)-> Result<(), Box<dyn Error>> {
-    branch_create(branch_name)?;
-    let minigit_path = find_minigit(&env::current_dir()?)?;
-    fs::write(minigit_path.join("HEAD"), &branch_name)?;
-    Ok(())
-}
 
-
-fn get_value_from_key(minigit_path: &PathBuf, key: &String)-> Result<Vec<u8>, std::io::Error> {
-    fs::read(minigit_path.join("objects").join(&key[0..2]).join(&key[2..]))
-}
-
-fn create_file_from_key(minigit_path: &PathBuf, path: &PathBuf, key: &String)-> Result<(), Box<dyn Error>> {
-    let value = & get_value_from_key(minigit_path, key)?;
-    if &value[0..5] != b"blob\0" {
-        return Err("create file from key failed: value type isn't blob".into());
+    #[test]
+    fn test_run()-> Result<()> {
+        let path = env::temp_dir().join(format!("minigit-run-{}", std::process::id()));
+        let config: Config = Config{operate:"init".to_string(), argument:vec![path.display().to_string()]};
+        run(&config)?;
+        assert!(path.join(".minigit").is_dir());
+        fs::remove_dir_all(path)?;
+        Ok(())
     }
-    // 创建文件并将解压的文件内容写入
-    let mut z = ZlibDecoder::new(File::create(path)?);
-    z.write_all(&value[5..])?;
-    Ok(())
-}
 
+    #[test]
+    fn test_init()-> Result<()> {
+        let repository = test_repository("init")?;
+        assert!(repository.minigit_path().join("HEAD").is_file());
+        assert_eq!(repository.current_branch()?, "master");
+        let sub_path = repository.root_path().join("sub");
+        fs::create_dir(&sub_path)?;
+        assert_eq!(Repository::discover(&sub_path)?.root_path(), repository.root_path());
+        assert!(matches!(Repository::open(&sub_path), Err(Error::NotARepository(_))));
+        // 再次初始化时保留已有的提交，不能修改对象格式
+        add_files(&repository)?;
+        let key = repository.commit("master", "first")?;
+        let repository = Repository::init(repository.root_path())?;
+        assert_eq!(repository.log(None, None)?[0].key, key);
+        let options = InitOptions { object_format: ObjectFormat::Git, ..Default::default() };
+        assert!(matches!(Repository::init_with_options(repository.root_path(), &options), Err(Error::Usage(_))));
+        Ok(())
+    }
 
-fn create_tree_from_key(minigit_path: &PathBuf, path: &PathBuf, key: &String)-> Result<(), Box<dyn Error>> {
-    fs::create_dir_all(path)?;
-    let value = get_value_from_key(minigit_path, key)?;
-    if &value[0..5] != b"tree\0" {
-        return Err("create tree from key failed: value type isn't tree".into());
+    /**
+     * 在仓库中创建'1.txt'和'test_dir/2.txt'并保存到暂存区
+     */
+    fn add_files(repository: &Repository)-> Result<()> {
+        let path = repository.root_path();
+        fs::write(path.join("1.txt"), b"Hello First World!")?;
+        fs::create_dir_all(path.join("test_dir"))?;
+        fs::write(path.join("test_dir").join("2.txt"), b"Hello Second World!")?;
+        repository.add(&[path])?;
+        Ok(())
     }
-    let mut dirs = value[5..].split(|&b| b == b'\0').map(|b| b.to_vec()).collect::<Vec<Vec<u8>>>();
-    dirs.pop();
-    for dir in dirs.iter() {
-        let file_type = &dir[0..4];
-        let file_key = &String::from_utf8(dir[5..45].to_vec())?;
-        let file_name_as_byte = dir[46..].to_vec();
-        let file_name = unsafe{OsString::from_encoded_bytes_unchecked(file_name_as_byte)};
-        let file_path = path.join(file_name);
-        if file_type == b"blob" {
-            create_file_from_key(minigit_path, &file_path, file_key)?;
-        }
-        else if file_type == b"tree" {
-            create_tree_from_key(minigit_path, &file_path, file_key)?;
+
+    #[test]
+    fn test_git_format()-> Result<()> {
+        let path = env::temp_dir().join(format!("minigit-git-format-{}", std::process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
         }
+        fs::create_dir_all(&path)?;
+        let options = InitOptions { object_format: ObjectFormat::Git, ..Default::default() };
+        let repository = Repository::init_with_options(&path, &options)?;
+        assert_eq!(Repository::open(&path)?.object_format(), ObjectFormat::Git);
+        // 设置保存在'[minigit]'小节中，其他小节的同名设置不影响minigit，旧版本没有小节的config也能读取
+        let config_path = repository.minigit_path().join("config");
+        assert!(fs::read_to_string(&config_path)?.starts_with("[minigit]\n"));
+        fs::write(&config_path, "[core]\n\tformat = minigit\n[minigit]\n\tformat = git\n")?;
+        assert_eq!(Repository::open(&path)?.object_format(), ObjectFormat::Git);
+        fs::write(&config_path, "format = git\nhash = sha1\n")?;
+        assert_eq!(Repository::open(&path)?.object_format(), ObjectFormat::Git);
+        fs::write(path.join("hello.txt"), "Hello World!\n")?;
+        repository.add(&["hello.txt"])?;
+        // 与 'git hash-object hello.txt' 的结果相同
+        let blob_path = repository.minigit_path().join("objects/98/0a0d5f19a64b4b30a87d4206aade58726b60e3");
+        assert!(blob_path.is_file());
+        repository.commit("master", "first commit")?;
+        repository.checkout_new_branch("second_branch")?;
+        fs::write(path.join("hello.txt"), "Hello Second World!\n")?;
+        repository.add(&["hello.txt"])?;
+        repository.commit("second", "second commit")?;
+        repository.checkout("master")?;
+        assert_eq!(fs::read_to_string(path.join("hello.txt"))?, "Hello World!\n");
+        assert!(matches!(repository.merge("second_branch", "master")?, MergeResult::FastForward(_)));
+        assert_eq!(repository.log(None, None)?.len(), 2);
+        Ok(())
     }
-    Ok(())
-}
-
 
-fn checkout(branch_name: &String)-> Result<(), Box<dyn Error>> {
-    let minigit_path = find_minigit(& env::current_dir()?)?;
-    let root_path = match minigit_path.parent() {
-        None=> return Err("checkout failed: can't get repository path".into()),
-        Some(r)=> r.to_path_buf(),
-    };
-    let now_branch_name = fs::read_to_string(minigit_path.join("HEAD"))?;
-    if &now_branch_name == branch_name {
-        return Ok(());
-    }
-    let branch_path = minigit_path.join("refs").join("heads").join(branch_name);
-    if !branch_path.is_file() {
-        return Err(format!("checkout branch {} failed: no such branch",branch_name).into());
-    }
-    let commit_key = fs::read_to_string(branch_path)?;
-    let commit_value = get_value_from_key(&minigit_path, &commit_key)?;
-    // get root_tree_key
-    let tree_index = commit_value.iter().rposition(|&b| b == b'\n').unwrap();
-    let tree_key = String::from_utf8(commit_value[(tree_index + 6)..].to_vec())?;
-    // delete_all root_path without minigit path
-    let ignore = OsString::from(".minigit");
-    for entry in root_path.read_dir()? {
-        let entry = entry?;
-        let file_type = entry.file_type()?;
-        let file_name = entry.file_name();
-        let file_path = entry.path();
-        if file_type.is_file() {
-            fs::remove_file(file_path)?;
-        }
-        else if file_type.is_dir() {
-            if file_name != ignore {
-                fs::remove_dir_all(file_path)?;
+    #[test]
+    fn test_sha256()-> Result<()> {
+        for object_format in [ObjectFormat::Minigit, ObjectFormat::Git] {
+            let path = env::temp_dir().join(format!("minigit-sha256-{}-{}", object_format.as_str(), std::process::id()));
+            if path.exists() {
+                fs::remove_dir_all(&path)?;
             }
+            fs::create_dir_all(path.join("dir"))?;
+            let options = InitOptions { object_format, hash_algorithm: HashAlgorithm::Sha256 };
+            let repository = Repository::init_with_options(&path, &options)?;
+            assert_eq!(Repository::open(&path)?.hash_algorithm(), HashAlgorithm::Sha256);
+            // git格式的sha256仓库需要记录git的objectFormat
+            let config = fs::read_to_string(repository.minigit_path().join("config"))?;
+            assert_eq!(config.contains("objectFormat = sha256"), object_format == ObjectFormat::Git);
+            fs::write(path.join("dir").join("a.txt"), "1\n2\n3\n")?;
+            repository.add(&[&path])?;
+            let key = repository.commit("master", "first commit")?;
+            assert_eq!(key.len(), 64);
+            repository.checkout_new_branch("second_branch")?;
+            fs::write(path.join("dir").join("a.txt"), "1\n2\nsecond\n")?;
+            repository.add(&[&path])?;
+            repository.commit("second", "second commit")?;
+            repository.checkout("master")?;
+            fs::write(path.join("dir").join("a.txt"), "master\n2\n3\n")?;
+            repository.add(&[&path])?;
+            repository.commit("master", "master commit")?;
+            assert!(matches!(repository.merge("second_branch", "master")?, MergeResult::Merged(_)));
+            assert_eq!(fs::read_to_string(path.join("dir").join("a.txt"))?, "master\n2\nsecond\n");
         }
-        else {
-            return Err("checkout failed: can't delete symlink file under repository path".into());
-        }
+        Ok(())
     }
-    // build new repository without index
-    create_tree_from_key(&minigit_path, &root_path, &tree_key)?;
-    // update index file. how to do? (choose 1 : rm and create a new idnex file ,then save_object(root_path))
-    File::create(minigit_path.join("index"))?;
-    save_object(&root_path)?;
-    // move HEAD ptr
-    fs::write(minigit_path.join("HEAD"), branch_name)?;
-    Ok(())
-}
 
-
-
-
-fn get_parent_commit(minigit_path: &PathBuf, commit_key: &String)-> Result<String, Box<dyn Error>> {
-    let commit_value = get_value_from_key(minigit_path, commit_key)?;
-    if &commit_value[0..7] != b"commit\0" {
-        return Err("get_parent_commit failed: key type isn't commit".into());
-    }
-    if commit_value[14] == b'\0' {
-        return Ok(String::from("\0"));
+    #[test]
+    fn test_add()-> Result<()> {
+        let repository = test_repository("add")?;
+        add_files(&repository)?;
+        let status = repository.status()?;
+        assert_eq!(status.staged, vec![("1.txt".to_string(), FileStatus::New), ("test_dir/2.txt".to_string(), FileStatus::New)]);
+        assert!(status.unstaged.is_empty() && status.untracked.is_empty());
+        assert!(matches!(repository.add(&[env::temp_dir()]), Err(Error::OutsideRepository(_))));
+        Ok(())
     }
-    return Ok(String::from_utf8(commit_value[14..54].to_vec())?);
-}
 
-fn find_both_ancestor(minigit_path: &PathBuf, commit_key1: &String, commit_key2: &String)-> Result<String, Box<dyn Error>> {
-    let mut k1 = commit_key1.clone();
-    let mut k2 = commit_key2.clone();
-    while k1 != k2 {
-        if k1 == "\0".to_string() {
-            k1 = commit_key2.clone();
-        }
-        else {
-            k1 = get_parent_commit(minigit_path, &k1)?;
-        }
-        if k2 == "\0".to_string() {
-            k2 = commit_key1.clone();
-        }
-        else {
-            k2 = get_parent_commit(minigit_path, &k2)?;
-        }
+    #[test]
+    fn test_add_cached()-> Result<()> {
+        let repository = test_repository("add-cached")?;
+        let path = repository.root_path().join("1.txt");
+        fs::write(&path, "cached")?;
+        // 保证文件的修改时间早于index的写入时间
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        repository.add(&["1.txt"])?;
+        let key = repository.hash_algorithm().hash(&Blob { data: b"cached".to_vec() }.serialize(repository.object_format()));
+        let object_path = repository.minigit_path().join("objects").join(&key[..2]).join(&key[2..]);
+        assert!(object_path.is_file());
+        // 文件状态没有变化时不会重新读取文件，所以删除的对象不会被重新写入
+        fs::remove_file(&object_path)?;
+        repository.add(&["1.txt"])?;
+        assert!(!object_path.exists());
+        assert!(repository.status()?.unstaged.is_empty());
+        fs::write(&path, "changed")?;
+        assert_eq!(repository.status()?.unstaged, vec![("1.txt".to_string(), FileStatus::Modified)]);
+        Ok(())
     }
-    Ok(k1)
-}
-
 
-
-fn get_diff_from_vec<T>(v1: &Vec<T>, v2: &Vec<T>)-> Result<Vec<T>, Box<dyn Error>> {
-    todo!();
-}
-
-fn diff(minigit_path: &PathBuf, key1: &String, key2: &String)-> Result<Vec<u8>, Box<dyn Error>> {
-    let mut blobs_value = Vec::new();
-    for key in vec![key1, key2] {
-        let buf = get_value_from_key(minigit_path, key)?;
-        // 解压value
-        let mut z = flate2::read::ZlibDecoder::new(&buf[..]);
-        let mut value = Vec::new();
-        z.read(&mut value)?;
-        let value_line: Vec<Vec<u8>>;
-        if &value[0..5] != b"blob\0" {
-            return Err("merge blob failed: key type is not blob".into());
-        }
-        if value.len() == 5 {
-            value_line = Vec::new();
-        }
-        else {
-            value_line = value[5..].split(|&b| b == b'\n').map(|str| str.to_vec()).collect::<Vec<Vec<u8>>>();
-        }
-        blobs_value.push(value_line);
+    #[test]
+    fn test_ignore()-> Result<()> {
+        let repository = test_repository("ignore")?;
+        let root_path = repository.root_path();
+        fs::write(root_path.join(".minigitignore"), "target/\n*.swp\n")?;
+        fs::create_dir_all(root_path.join("target"))?;
+        fs::write(root_path.join("target").join("out"), "build output")?;
+        fs::write(root_path.join("a.txt.swp"), "swap")?;
+        fs::create_dir_all(repository.minigit_path().join("info"))?;
+        fs::write(repository.minigit_path().join("info").join("exclude"), "*.log\n")?;
+        fs::write(root_path.join("debug.log"), "log")?;
+        add_files(&repository)?;
+        fs::write(root_path.join("test_dir").join("2.txt.swp"), "swap")?;
+        let status = repository.status()?;
+        assert!(status.untracked.is_empty());
+        assert_eq!(status.staged.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), vec![".minigitignore", "1.txt", "test_dir/2.txt"]);
+        assert!(matches!(repository.add(&["target"]), Err(Error::IgnoredPath(_))));
+        repository.commit("master", "first commit")?;
+        repository.checkout_new_branch("dev")?;
+        fs::write(root_path.join("dev.txt"), "dev")?;
+        repository.add(&["dev.txt"])?;
+        repository.commit("master", "dev commit")?;
+        // 切换分支时被忽略的文件保持不变
+        repository.checkout("master")?;
+        assert!(!root_path.join("dev.txt").exists());
+        assert!(root_path.join("target").join("out").is_file() && root_path.join("a.txt.swp").is_file() && root_path.join("debug.log").is_file());
+        assert!(root_path.join("test_dir").join("2.txt.swp").is_file());
+        Ok(())
     }
-    // 对比两个向量，通过算法找到不同
-    let _re = get_diff_from_vec(&blobs_value[0], &blobs_value[1])?;
-    todo!();
-}
-
-
 
-
-fn merge_blob(branch_name: &String, minigit_path: &PathBuf, path: &PathBuf, blobs_key: &Vec<String>)-> Result<bool, Box<dyn Error>> {
-    let mut blobs_value: Vec<Vec<u8>> = Vec::new();
-    let mut no_conflict = true;
-    for key in blobs_key {
-        let buf = get_value_from_key(minigit_path, key)?;
-        if &buf[..5] != b"blob\0" {
-            return Err("merge blob failed: key type is not blob".into());
-        }
-        // 解压value
-        let mut z = flate2::read::ZlibDecoder::new(&buf[5..]);
-        let mut value = Vec::new();
-        z.read_to_end(&mut value)?;
-        blobs_value.push(value);
-    }
-    // 合并文件数据，并且标出冲突
-    let file_value;
-    if blobs_key[0] == blobs_key[1] {
-        file_value = blobs_value[0].clone();
-    }
-    else {
-        no_conflict = false;
-        println!("Conflict at: {}", path.display());
-        let now_branch_name = fs::read_to_string(minigit_path.join("HEAD"))?;
-        let mut v = vec![format!("========== {now_branch_name}").into_bytes(), blobs_value[0].clone(),
-                                   format!("========== {branch_name}").into_bytes(), blobs_value[1].clone()];
-        if blobs_key.len() == 3 {
-            v.push(b"========== common ancestor".to_vec());
-            v.push(blobs_value[2].clone());
-        }
-        file_value = v.join(&b'\n');
+    #[test]
+    fn test_rm()-> Result<()> {
+        let repository = test_repository("rm")?;
+        add_files(&repository)?;
+        repository.rm(&[Path::new("test_dir").join("2.txt")])?;
+        assert!(!repository.root_path().join("test_dir").join("2.txt").exists());
+        assert_eq!(repository.status()?.staged, vec![("1.txt".to_string(), FileStatus::New)]);
+        // '--cached'只取消跟踪，文件仍然留在工作区中
+        repository.rm_cached(&["1.txt"])?;
+        assert!(repository.root_path().join("1.txt").is_file());
+        let status = repository.status()?;
+        assert!(status.staged.is_empty());
+        assert_eq!(status.untracked, vec!["1.txt".to_string()]);
+        assert!(matches!(repository.rm_cached(&["1.txt"]), Err(Error::InvalidPath(_))));
+        Ok(())
     }
-    fs::write(path, &file_value)?;
-    Ok(no_conflict)
-}
 
-
-fn merge_tree(branch_name: &String, minigit_path: &PathBuf, path: &PathBuf, trees_key: &Vec<String>)-> Result<bool, Box<dyn Error>> {
-    fs::create_dir_all(&path)?;
-    let mut re = true;
-    let mut trees_value: Vec<Vec<Vec<u8>>> = Vec::new();
-    let mut hashmap: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
-    if trees_key.len() == 3 {
-        let mut buf = get_value_from_key(minigit_path, &trees_key[2])?;
-        if &buf[0..5] != b"tree\0" {
-            return Err("merge tree failed: key type is not tree".into());
-        }
-        if buf.len() > 5 {
-            buf.pop();
-            for common_value in buf[5..].split(|&b| b == b'\0') {
-                let mut map_key = common_value[46..].to_vec();
-                map_key.append(&mut common_value[..4].to_vec());
-                let map_value = common_value[5..45].to_vec();
-                hashmap.insert(map_key, map_value);
-            }
-        }
-    }
-    for key in &trees_key[..2] {
-        let mut buf = get_value_from_key(minigit_path, key)?;
-        let mut value: Vec<Vec<u8>>;
-        if &buf[0..5] != b"tree\0" {
-            return Err("merge tree failed: key type is not tree".into());
-        }
-        if buf.len() == 5 {
-            value = Vec::new();
-        }
-        else {
-            buf.pop();
-            value = buf[5..].split(|&b| b == b'\0').map(|v| v.to_vec()).collect::<Vec<Vec<u8>>>();
-            value.sort_unstable_by(|x,y| {
-                                        let ord = x[46..].cmp(&y[46..]);
-                                        match ord {
-                                            Ordering::Equal => return x[0..4].cmp(&y[0..4]),
-                                            _=> return ord, 
-                                        }});
-        }
-        trees_value.push(value);
-    }
-    let (v1, v2) = (&trees_value[0], &trees_value[1]);
-    let (n1, n2)  = (v1.len(), v2.len());
-    let (mut it1, mut it2) = (0, 0);
-    while it1 < n1 && it2 < n2 {
-        let (type1, key1, name1) = (&v1[it1][..4], &v1[it1][5..45], &v1[it1][46..]);
-        let (type2, key2, name2) = (&v2[it2][..4], &v2[it2][5..45], &v2[it2][46..]);
-        let mut ord = name1.cmp(name2);
-        if let Ordering::Equal = ord {
-            ord = type1.cmp(type2);
-        }
-        match ord {
-            Ordering::Equal=> {
-                it1 = it1 + 1;
-                it2 = it2 + 1;
-                let mut keys: Vec<String> = vec![String::from_utf8(key1.to_vec())?, String::from_utf8(key2.to_vec())?];
-                if trees_key.len() == 3 {
-                    let mut com_key = name1.to_vec();
-                    com_key.append(&mut type1.to_vec());
-                    let com_value = hashmap.get(&com_key);
-                    if let Some(c) = com_value {
-                        keys.push(String::from_utf8(c.clone())?);
-                    }
-                }
-                let name = unsafe{OsString::from_encoded_bytes_unchecked(name1.to_vec())};
-                if type1 == b"tree" {
-                    re = re && merge_tree(branch_name, minigit_path, &path.join(&name), &keys)?;
-                }
-                else {
-                    re = re && merge_blob(branch_name, minigit_path, &path.join(&name), &keys)?;
-                }
-            },
-            Ordering::Less=> {
-                it1 = it1 + 1;
-                let mut is_file_in_common_commit = false;
-                if trees_key.len() == 3 {
-                    let mut com_key = name1.to_vec();
-                    com_key.append(&mut type1.to_vec());
-                    let com_value = hashmap.get(&com_key);
-                    if let Some(_c) = com_value {
-                        is_file_in_common_commit = true;
-                    }
-                }
-                let name = unsafe{OsString::from_encoded_bytes_unchecked(name1.to_vec())};
-                if !is_file_in_common_commit {
-                    if type1 == b"tree" {
-                        create_tree_from_key(minigit_path, &path.join(&name), &String::from_utf8(key1.to_vec())?)?;
-                    }
-                    else {
-                        create_file_from_key(minigit_path, &path.join(&name), &String::from_utf8(key1.to_vec())?)?;
-                    }
-                }
-            },
-            Ordering::Greater=> {
-                it2 = it2 + 1;
-                let mut is_file_in_common_commit = false;
-                if trees_key.len() == 3 {
-                    let mut com_key = name2.to_vec();
-                    com_key.append(&mut type2.to_vec());
-                    let com_value = hashmap.get(&com_key);
-                    if let Some(_c) = com_value {
-                        is_file_in_common_commit = true;
-                    }
-                }
-                let name = unsafe{OsString::from_encoded_bytes_unchecked(name2.to_vec())};
-                if !is_file_in_common_commit {
-                    if type2 == b"tree" {
-                        create_tree_from_key(minigit_path, &path.join(&name), &String::from_utf8(key2.to_vec())?)?;
-                    }
-                    else {
-                        create_file_from_key(minigit_path, &path.join(&name), &String::from_utf8(key2.to_vec())?)?;
-                    }
-                }
-            },
-        }
-    }
-    // 这两个循环只可能运行一个
-    while it1 < n1 {
-        let (type1, key1, name1) = (&v1[it1][..4], &v1[it1][5..45], &v1[it1][46..]);
-        it1 = it1 + 1;
-        let mut is_file_in_common_commit = false;
-        if trees_key.len() == 3 {
-            let mut com_key = name1.to_vec();
-            com_key.append(&mut type1.to_vec());
-            let com_value = hashmap.get(&com_key);
-            if let Some(_c) = com_value {
-                is_file_in_common_commit = true;
-            }
-        }
-        let name = unsafe{OsString::from_encoded_bytes_unchecked(name1.to_vec())};
-        if !is_file_in_common_commit {
-            if type1 == b"tree" {
-                create_tree_from_key(minigit_path, &path.join(&name), &String::from_utf8(key1.to_vec())?)?;
-            }
-            else {
-                create_file_from_key(minigit_path, &path.join(&name), &String::from_utf8(key1.to_vec())?)?;
-            }
-        }
-    }
-    // 这两个循环只可能运行一个
-    while it2 < n2 {
-        let (type2, key2, name2) = (&v2[it2][..4], &v2[it2][5..45], &v2[it2][46..]);
-        it2 = it2 + 1;
-        let mut is_file_in_common_commit = false;
-        if trees_key.len() == 3 {
-            let mut com_key = name2.to_vec();
-            com_key.append(&mut type2.to_vec());
-            let com_value = hashmap.get(&com_key);
-            if let Some(_c) = com_value {
-                is_file_in_common_commit = true;
-            }
-        }
-        let name = unsafe{OsString::from_encoded_bytes_unchecked(name2.to_vec())};
-        if !is_file_in_common_commit {
-            if type2 == b"tree" {
-                create_tree_from_key(minigit_path, &path.join(&name), &String::from_utf8(key2.to_vec())?)?;
-            }
-            else {
-                create_file_from_key(minigit_path, &path.join(&name), &String::from_utf8(key2.to_vec())?)?;
-            }
-        }
+    #[test]
+    fn test_reset()-> Result<()> {
+        let repository = test_repository("reset")?;
+        let root_path = repository.root_path();
+        add_files(&repository)?;
+        let first = repository.commit("master", "first")?;
+        fs::write(root_path.join("1.txt"), "second version")?;
+        fs::write(root_path.join("3.txt"), "new file")?;
+        repository.add(&["1.txt", "3.txt"])?;
+        // 取消暂存：暂存区回到当前提交，工作区不变
+        repository.reset_paths(None, &["1.txt", "3.txt"])?;
+        let status = repository.status()?;
+        assert!(status.staged.is_empty());
+        assert_eq!(status.unstaged, vec![("1.txt".to_string(), FileStatus::Modified)]);
+        assert_eq!(status.untracked, vec!["3.txt".to_string()]);
+        repository.add(&["1.txt"])?;
+        let second = repository.commit("master", "second")?;
+        // soft只移动分支，暂存区中仍然是第二个提交的内容
+        assert_eq!(repository.reset(Some(&first), ResetMode::Soft)?, first);
+        assert_eq!(repository.status()?.staged, vec![("1.txt".to_string(), FileStatus::Modified)]);
+        assert_eq!(fs::read_to_string(repository.minigit_path().join("ORIG_HEAD"))?, second);
+        repository.reset(Some(&second), ResetMode::Mixed)?;
+        repository.reset(Some(&first), ResetMode::Mixed)?;
+        let status = repository.status()?;
+        assert!(status.staged.is_empty());
+        assert_eq!(status.unstaged, vec![("1.txt".to_string(), FileStatus::Modified)]);
+        repository.reset(None, ResetMode::Hard)?;
+        assert_eq!(fs::read_to_string(root_path.join("1.txt"))?, "Hello First World!");
+        assert!(matches!(repository.reset(Some("missing"), ResetMode::Hard), Err(Error::UnknownRevision(_))));
+        assert_eq!(repository.reflog(None)?[0].message, "reset: moving to HEAD");
+        Ok(())
     }
-    Ok(re)
-}
-
 
-
-fn merge(branch_name: &String)-> Result<(), Box<dyn Error>> {
-    let minigit_path = find_minigit(&env::current_dir()?)?;
-    let now_branch_name = fs::read_to_string(minigit_path.join("HEAD"))?;
-    if *branch_name == now_branch_name {
-        return Ok(())
-    }
-    let branchs_path = minigit_path.join("refs").join("heads");
-    let now_branch_path = branchs_path.join(&now_branch_name);
-    let branch_path = branchs_path.join(branch_name);
-    if !branch_path.is_file() {
-        return Err(format!("merge failed: no such branch named {branch_name}").into());
-    }
-    if !now_branch_path.is_file() {
-        return Err("merge failed: can't find now branch".into());
-    }
-    let commit_key = fs::read_to_string(&branch_path)?;
-    let now_commit_key = fs::read_to_string(&now_branch_path)?;
-    let common_commit_key = find_both_ancestor(&minigit_path, &commit_key, &now_commit_key)?;
-    if common_commit_key == "\0".to_string() {
-        return Err(format!("merge failed: branch {branch_name} and branch {now_branch_name} have no common ancestor commit").into());
-    }
-    // 如果没有分支，则快速合并，将指针移动到最新提交即可
-    if common_commit_key == commit_key {
-        // 说明此时已经在最新提交上，不用操作直接返回
-        return Ok(())
-    }
-    if common_commit_key == now_commit_key {
-        // 说明此时要合并的分支比现在的分支进度更远，将指针移动到要合并的分支的最新提交即可
-        fs::write(&now_branch_path, &commit_key)?;
+    #[test]
+    fn test_restore()-> Result<()> {
+        let repository = test_repository("restore")?;
+        let root_path = repository.root_path();
+        add_files(&repository)?;
+        let first = repository.commit("master", "first")?;
+        fs::write(root_path.join("1.txt"), "second version")?;
+        repository.add(&["1.txt"])?;
+        repository.commit("master", "second")?;
+        // 从暂存区恢复被修改和删除的文件，其他文件不受影响
+        fs::write(root_path.join("1.txt"), "broken")?;
+        fs::remove_dir_all(root_path.join("test_dir"))?;
+        fs::write(root_path.join("3.txt"), "untracked")?;
+        repository.restore(None, false, &["1.txt", "test_dir"])?;
+        assert_eq!(fs::read_to_string(root_path.join("1.txt"))?, "second version");
+        assert_eq!(fs::read_to_string(root_path.join("test_dir").join("2.txt"))?, "Hello Second World!");
+        assert!(root_path.join("3.txt").is_file());
+        assert!(repository.status()?.unstaged.is_empty());
+        // 从提交恢复时暂存区不变
+        repository.restore(Some(&first), false, &["1.txt"])?;
+        assert_eq!(fs::read_to_string(root_path.join("1.txt"))?, "Hello First World!");
+        let status = repository.status()?;
+        assert!(status.staged.is_empty());
+        assert_eq!(status.unstaged, vec![("1.txt".to_string(), FileStatus::Modified)]);
+        repository.restore(Some(&first), true, &["1.txt"])?;
+        assert_eq!(repository.status()?.staged, vec![("1.txt".to_string(), FileStatus::Modified)]);
+        assert!(matches!(repository.restore(None, false, &["3.txt"]), Err(Error::InvalidPath(_))));
+        Ok(())
     }
-    // 如果有分支，则需要三路合并
-    // 获得三个提交的tree-key
-    let commits_key = vec![&now_commit_key, &commit_key, &common_commit_key];
-    let mut trees_key = Vec::new();
-    for key in commits_key {
-        let value = get_value_from_key(&minigit_path, key)?;
-        let tree_index = value.iter().rposition(|&b| b == b'\n').unwrap();
-        let tree_key = String::from_utf8(value[(tree_index + 6)..].to_vec())?;
-        trees_key.push(tree_key);
+
+    #[test]
+    fn test_commit()-> Result<()> {
+        let repository = test_repository("commit")?;
+        add_files(&repository)?;
+        let key = repository.commit("master", "test first commit")?;
+        let log = repository.log(None, None)?;
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].key, key);
+        assert_eq!(log[0].message, "test first commit");
+        assert!(repository.status()?.staged.is_empty());
+        Ok(())
     }
-    // delete_all root_path without minigit path
-    let root_path = match minigit_path.parent() {
-        None=> return Err("checkout failed: can't get repository path".into()),
-        Some(r)=> r.to_path_buf(),
-    };
-    let ignore = OsString::from(".minigit");
-    for entry in root_path.read_dir()? {
-        let entry = entry?;
-        let file_type = entry.file_type()?;
-        let file_name = entry.file_name();
-        let file_path = entry.path();
-        if file_type.is_file() {
-            fs::remove_file(file_path)?;
-        }
-        else if file_type.is_dir() {
-            if file_name != ignore {
-                fs::remove_dir_all(file_path)?;
-            }
-        }
-        else {
-            return Err("checkout failed: can't delete symlink file under repository path".into());
+
+    #[test]
+    fn test_branch()-> Result<()> {
+        let repository = test_repository("branch")?;
+        add_files(&repository)?;
+        repository.commit("master", "test first commit")?;
+        repository.create_branch("second_branch")?;
+        assert_eq!(repository.branches()?, vec!["master", "second_branch"]);
+        assert!(matches!(repository.create_branch("second_branch"), Err(Error::BranchExists(name)) if name == "second_branch"));
+        assert!(matches!(repository.delete_branch("master"), Err(Error::DeleteCurrentBranch(_))));
+        assert!(matches!(repository.checkout("third_branch"), Err(Error::BranchNotFound(_))));
+        repository.delete_branch("second_branch")?;
+        assert_eq!(repository.branches()?, vec!["master"]);
+        // 分支名字不能写到refs/heads之外，也不能与版本表达式冲突
+        for name in ["", "../../x", "/x", "a/b", "..", ".hidden", "x.lock", "a\tb", "a~1", "a^", "a:b", "a@{1}", "@", "HEAD"] {
+            assert!(matches!(repository.create_branch(name), Err(Error::Usage(_))), "{name}");
         }
+        assert!(matches!(repository.delete_branch("../../HEAD"), Err(Error::Usage(_))));
+        assert!(!repository.root_path().join("x").exists());
+        Ok(())
     }
-    // 进行三路合并
-    let no_conflict = merge_tree(branch_name, &minigit_path, &root_path, &trees_key)?;
-    // 提交合并后的工作目录
-    if no_conflict {
-        let author = env::var("USERNAME")?;
-        commit(&author, &format!("merge {branch_name} to {now_branch_name}"))?;
-    }
-    Ok(())
-}
-
-
 
+    #[test]
+    fn test_reflog()-> Result<()> {
+        let mut repository = test_repository("reflog")?;
+        repository.set_identity("me");
+        add_files(&repository)?;
+        let first = repository.commit("master", "first commit")?;
+        repository.checkout_new_branch("dev")?;
+        fs::write(repository.root_path().join("dev.txt"), "dev")?;
+        repository.add(&["dev.txt"])?;
+        let second = repository.commit("master", "dev commit")?;
+        repository.checkout("master")?;
+        repository.delete_branch("dev")?;
+        let messages = |entries: Vec<ReflogEntry>| entries.into_iter().map(|e| e.message).collect::<Vec<_>>();
+        assert_eq!(messages(repository.reflog(None)?), vec![
+            "checkout: moving from dev to master", "commit: dev commit", "checkout: moving from master to dev", "commit (initial): first commit",
+        ]);
+        // 被删除的分支的提交仍然可以从引用日志中找回
+        let dev = repository.reflog(Some("dev"))?;
+        assert_eq!(messages(dev.clone()), vec!["branch: deleted", "commit: dev commit", "branch: Created from master"]);
+        assert_eq!((dev[0].old_key.as_str(), dev[0].identity.as_str()), (second.as_str(), "me"));
+        assert_eq!(dev[2].new_key, first);
+        assert!(matches!(repository.reflog(Some("none")), Err(Error::BranchNotFound(_))));
+        // 分支名不能跳出.minigit/logs
+        assert!(matches!(repository.reflog(Some("../../HEAD")), Err(Error::Usage(_))));
+        assert!(matches!(repository.resolve_revision("../../HEAD@{0}"), Err(Error::Usage(_))));
+        Ok(())
+    }
 
-#[cfg(test)]
-mod test{
+    #[test]
+    fn test_checkout()-> Result<()> {
+        let repository = test_repository("checkout")?;
+        let root_path = repository.root_path();
+        add_files(&repository)?;
+        repository.commit("master", "test first commit")?;
+        repository.create_branch("second_branch")?;
+        fs::write(root_path.join("master.txt"), "This is master branch")?;
+        repository.add(&["master.txt"])?;
+        repository.commit("master", "master commit")?;
+        let modified = fs::metadata(root_path.join("1.txt"))?.modified()?;
+        repository.checkout("second_branch")?;
+        assert_eq!(repository.current_branch()?, "second_branch");
+        assert!(!root_path.join("master.txt").exists());
+        // 两个分支中相同的文件不会被重写
+        assert_eq!(fs::metadata(root_path.join("1.txt"))?.modified()?, modified);
+        fs::write(root_path.join("second.txt"), "Test checkout")?;
+        repository.add(&["second.txt"])?;
+        repository.commit("second", "test_second")?;
+        repository.checkout("master")?;
+        assert!(root_path.join("master.txt").is_file());
+        assert!(!root_path.join("second.txt").exists());
+        assert_eq!(fs::read_to_string(root_path.join("test_dir").join("2.txt"))?, "Hello Second World!");
+        // 切换时会被重写的文件上没有提交的修改和冲突的未跟踪文件会阻止切换，其他未跟踪的文件保留下来
+        fs::write(root_path.join("untracked.txt"), "keep me")?;
+        fs::write(root_path.join("master.txt"), "not committed")?;
+        assert!(matches!(repository.checkout("second_branch"), Err(Error::WouldOverwrite(paths)) if paths == vec![root_path.join("master.txt")]));
+        repository.restore(None, false, &["master.txt"])?;
+        fs::write(root_path.join("second.txt"), "in the way")?;
+        assert!(matches!(repository.checkout("second_branch"), Err(Error::WouldOverwrite(paths)) if paths == vec![root_path.join("second.txt")]));
+        assert!(matches!(repository.merge("second_branch", "master"), Err(Error::WouldOverwrite(_))));
+        repository.force_checkout("second_branch")?;
+        assert_eq!(fs::read_to_string(root_path.join("second.txt"))?, "Test checkout");
+        assert_eq!(fs::read_to_string(root_path.join("untracked.txt"))?, "keep me");
+        // 两个分支中相同的文件上的修改不影响切换，并且保留下来
+        fs::write(root_path.join("1.txt"), "not committed")?;
+        repository.checkout("master")?;
+        assert_eq!(fs::read_to_string(root_path.join("1.txt"))?, "not committed");
+        assert_eq!(repository.status()?.unstaged, vec![("1.txt".to_string(), FileStatus::Modified)]);
+        Ok(())
+    }
 
-    use super::*;
+    #[cfg(unix)]
     #[test]
-    fn test_build(){
-        let v = vec!["init","test"];
-        let it = v.iter().map(|x|{x.to_string()});
-        let config = Config::build(it).unwrap_or_else(|err| {
-            eprintln!("error at test build: {err}");
-            std::process::exit(1);
-        });
-        assert_eq!((config.operate,config.argument),(String::from("init"),vec![String::from("test")]));
-        //dbg!(config);
+    fn test_symlink()-> Result<()> {
+        use std::os::unix::fs::symlink;
+        let repository = test_repository("symlink")?;
+        let root_path = repository.root_path();
+        add_files(&repository)?;
+        symlink("1.txt", root_path.join("link"))?;
+        symlink("test_dir", root_path.join("dir_link"))?;
+        symlink("missing", root_path.join("broken"))?;
+        repository.add(&[root_path])?;
+        assert!(repository.status()?.unstaged.is_empty());
+        repository.commit("master", "add links")?;
+        repository.checkout_new_branch("dev")?;
+        fs::remove_file(root_path.join("link"))?;
+        symlink("test_dir/2.txt", root_path.join("link"))?;
+        assert_eq!(repository.status()?.unstaged, vec![("link".to_string(), FileStatus::Modified)]);
+        repository.add(&["link"])?;
+        repository.commit("master", "change link")?;
+        repository.checkout("master")?;
+        assert_eq!(fs::read_link(root_path.join("link"))?, Path::new("1.txt"));
+        assert_eq!(fs::read_link(root_path.join("broken"))?, Path::new("missing"));
+        // 只有一方修改了链接时合并使用修改后的链接
+        assert!(matches!(repository.merge("dev", "master")?, MergeResult::FastForward(_)));
+        assert_eq!(fs::read_link(root_path.join("link"))?, Path::new("test_dir/2.txt"));
+        assert!(root_path.join("test_dir").join("2.txt").is_file());
+        repository.rm(&["dir_link"])?;
+        assert!(root_path.join("test_dir").join("2.txt").is_file());
+        Ok(())
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_run(){
-        let config: Config = Config{operate:"init".to_string(), argument:vec!["test".to_string()]};
-        run(&config).unwrap_or_else(|err|{
-            eprintln!("error at test_init: {err}");
-        });
-        let path =  std::env::current_dir().unwrap_or_else(|err|{
-            eprintln!("test_run failed at get current dir: {err}");
-            std::process::exit(1);
-        });
-        assert!(path.join(&config.argument[0]).exists());
+    fn test_executable()-> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        let repository = test_repository("executable")?;
+        let root_path = repository.root_path();
+        let mode = |name: &str| fs::metadata(root_path.join(name)).map(|metadata| metadata.permissions().mode() & 0o111);
+        let chmod = |name: &str, mode: u32| fs::set_permissions(root_path.join(name), fs::Permissions::from_mode(mode));
+        add_files(&repository)?;
+        repository.commit("master", "first")?;
+        repository.checkout_new_branch("dev")?;
+        chmod("1.txt", 0o755)?;
+        assert_eq!(repository.status()?.unstaged, vec![("1.txt".to_string(), FileStatus::Modified)]);
+        assert_eq!(repository.diff_worktree(&["1.txt"], 3)?, b"diff --minigit a/1.txt b/1.txt\nold mode 100644\nnew mode 100755\n".to_vec());
+        repository.add(&["1.txt"])?;
+        repository.commit("master", "make 1.txt executable")?;
+        repository.checkout("master")?;
+        assert_eq!(mode("1.txt")?, 0);
+        // 一方修改了内容，另一方修改了模式时两者都被保留
+        fs::write(root_path.join("1.txt"), "changed on master\n")?;
+        repository.add(&["1.txt"])?;
+        repository.commit("master", "change 1.txt")?;
+        assert!(matches!(repository.merge("dev", "master")?, MergeResult::Merged(_)));
+        assert_eq!(mode("1.txt")?, 0o111);
+        assert_eq!(fs::read_to_string(root_path.join("1.txt"))?, "changed on master\n");
+        assert!(repository.status()?.unstaged.is_empty());
+        Ok(())
     }
 
+
     #[test]
-    fn test_init(){
-        let name = "test".to_string();
-        let _unused = init(&name).unwrap_or_else(|err|{
-            eprintln!("error at test_init: {err}");
-        });
-        let path = env::current_dir().unwrap();
-        assert!(path.join(name).is_dir());
+    fn test_log()-> Result<()> {
+        let repository = test_repository("log")?;
+        add_files(&repository)?;
+        let first = repository.commit("master", "first")?;
+        let mut keys = vec![first.clone()];
+        for message in ["second", "third"] {
+            fs::write(repository.root_path().join("1.txt"), message)?;
+            repository.add(&["1.txt"])?;
+            keys.insert(0, repository.commit("master", message)?);
+        }
+        let log = repository.log(None, None)?;
+        assert_eq!(log.iter().map(|entry| entry.key.clone()).collect::<Vec<_>>(), keys);
+        // 只读取需要输出的提交，更早的提交丢失时仍然可以查看最近的提交
+        fs::remove_file(repository.minigit_path().join("objects").join(&first[..2]).join(&first[2..]))?;
+        let log = repository.log(None, Some(2))?;
+        assert_eq!(log.iter().map(|entry| entry.message.as_str()).collect::<Vec<_>>(), vec!["third", "second"]);
+        assert!(repository.log(None, Some(0))?.is_empty());
+        assert!(repository.log(None, None).is_err());
+        assert_eq!(parse_number("12")?, 12);
+        for arg in ["", "+5", "-5", "5x"] {
+            assert!(matches!(parse_number(arg), Err(Error::Usage(_))));
+        }
+        Ok(())
     }
 
     #[test]
-    fn test_add()-> std::io::Result<()>{
-        let name = "test".to_string();
-        init(&name).unwrap_or_else(|err|{
-            eprintln!("error at test_add: {err}");
-        });
-        let mut path = env::current_dir().unwrap().join(name);
-        env::set_current_dir(&path)?;
-        let mut file1 = File::create(path.join("1.txt")).unwrap();
-        file1.write_all(b"Hello First World!")?;
-        path = path.join("test_dir");
-        fs::create_dir_all(&path)?;
-        let mut file2 = File::create(path.join("2.txt")).unwrap();
-        file2.write_all(b"Hello Second World!")?;
-        add(&vec!["*".to_string()]).unwrap_or_else(|err|{
-            eprintln!("error at test_add: {err}");
-        });
+    fn test_detached_head()-> Result<()> {
+        let repository = test_repository("detached")?;
+        let root_path = repository.root_path();
+        assert_eq!(fs::read_to_string(repository.minigit_path().join("HEAD"))?, "ref: refs/heads/master");
+        add_files(&repository)?;
+        let first = repository.commit("master", "first")?;
+        fs::write(root_path.join("1.txt"), "second")?;
+        repository.add(&["1.txt"])?;
+        let second = repository.commit("master", "second")?;
+        repository.checkout(&first)?;
+        assert_eq!(repository.head()?, Head::Detached(first.clone()));
+        assert!(matches!(repository.current_branch(), Err(Error::DetachedHead(_))));
+        assert_eq!(fs::read_to_string(root_path.join("1.txt"))?, "Hello First World!");
+        assert!(repository.status()?.detached);
+        // 分离状态下的提交只移动HEAD
+        fs::write(root_path.join("3.txt"), "detached")?;
+        repository.add(&["3.txt"])?;
+        let detached = repository.commit("master", "detached commit")?;
+        assert_eq!(repository.head()?, Head::Detached(detached.clone()));
+        assert_eq!(repository.log(None, None)?.iter().map(|entry| entry.key.clone()).collect::<Vec<_>>(), vec![detached.clone(), first]);
+        assert_eq!(repository.log(Some("master"), Some(1))?[0].key, second);
+        assert!(matches!(repository.merge("master", "master")?, MergeResult::Merged(_)));
+        assert!(matches!(repository.head()?, Head::Detached(key) if key != detached));
+        repository.create_branch("rescued")?;
+        repository.checkout("master")?;
+        assert_eq!(repository.head()?, Head::Branch("master".to_string()));
+        assert!(!root_path.join("3.txt").exists());
+        assert!(matches!(repository.merge("rescued", "master")?, MergeResult::FastForward(_)));
+        assert!(root_path.join("3.txt").is_file());
         Ok(())
     }
 
     #[test]
-    fn test_rm()-> std::io::Result<()>{
-        test_add()?;
-        rm(&vec!["test_dir\\2.txt".to_string()]).unwrap_or_else(|err| {
-            eprintln!("error at test_rm: {err}");
-        });
+    fn test_tag()-> Result<()> {
+        let repository = test_repository("tag")?;
+        let root_path = repository.root_path();
+        add_files(&repository)?;
+        let first = repository.commit("master", "first")?;
+        assert_eq!(repository.create_tag("v1", None)?, first);
+        assert!(matches!(repository.create_tag("v1", None), Err(Error::TagExists(_))));
+        fs::write(root_path.join("1.txt"), "second")?;
+        repository.add(&["1.txt"])?;
+        let second = repository.commit("master", "second")?;
+        // 附注标签指向标签对象，使用时被解开为它指向的提交
+        let key = repository.create_annotated_tag("v2", None, "me", "release")?;
+        assert_ne!(key, second);
+        assert_eq!(fs::read_to_string(repository.minigit_path().join("refs/tags/v2"))?, key);
+        assert_eq!(repository.create_tag("v2-light", Some("v2"))?, second);
+        assert_eq!(repository.tags()?, vec!["v1", "v2", "v2-light"]);
+        repository.checkout("v1")?;
+        assert_eq!(repository.head()?, Head::Detached(first.clone()));
+        assert_eq!(fs::read_to_string(root_path.join("1.txt"))?, "Hello First World!");
+        repository.checkout("master")?;
+        assert_eq!(repository.reset(Some("v2"), ResetMode::Soft)?, second);
+        assert_eq!(repository.delete_tag("v1")?, first);
+        assert!(matches!(repository.delete_tag("v1"), Err(Error::TagNotFound(_))));
+        assert!(matches!(repository.create_tag("a/b", None), Err(Error::Usage(_))));
+        assert!(matches!(repository.create_tag("../v1", None), Err(Error::Usage(_))));
+        assert!(matches!(repository.delete_tag("../heads/master"), Err(Error::Usage(_))));
         Ok(())
     }
 
     #[test]
-    fn test_commit()-> std::io::Result<()> {
-        test_add()?;
-        let author_name = "master".to_string();
-        let message = "test first commit".to_string();
-        commit(&author_name, &message).unwrap_or_else(|err|{
-            eprintln!("error at test_commit: {err}");
-        });
+    fn test_revision()-> Result<()> {
+        let repository = test_repository("revision")?;
+        let root_path = repository.root_path();
+        add_files(&repository)?;
+        let first = repository.commit("master", "first")?;
+        repository.create_branch_at("dev", &first[..8])?;
+        fs::write(root_path.join("1.txt"), "master")?;
+        repository.add(&["1.txt"])?;
+        let second = repository.commit("master", "second")?;
+        repository.checkout("dev")?;
+        fs::write(root_path.join("3.txt"), "dev")?;
+        repository.add(&["3.txt"])?;
+        let dev = repository.commit("master", "dev")?;
+        repository.checkout("master")?;
+        let merged = match repository.merge("dev", "master")? {
+            MergeResult::Merged(key)=> key,
+            other=> panic!("unexpected merge result {other:?}"),
+        };
+        repository.create_annotated_tag("v1", Some("HEAD^2"), "me", "dev release")?;
+        assert_eq!(repository.resolve_revision("HEAD")?, merged);
+        assert_eq!(repository.resolve_revision("@^1")?, second);
+        assert_eq!(repository.resolve_revision("master^2")?, dev);
+        assert_eq!(repository.resolve_revision("v1~1")?, first);
+        assert_eq!(repository.resolve_revision("HEAD~2^0")?, first);
+        assert_eq!(repository.resolve_revision("master@{1}")?, second);
+        assert_eq!(repository.resolve_revision("dev@{0}")?, dev);
+        assert_eq!(repository.resolve_revision(&merged[..5])?, merged);
+        assert_eq!(repository.resolve_revision("HEAD~1:1.txt")?, repository.resolve_revision("HEAD:1.txt")?);
+        assert!(matches!(repository.resolve_revision("HEAD~3"), Err(Error::UnknownRevision(_))));
+        assert!(matches!(repository.resolve_revision("HEAD^3"), Err(Error::UnknownRevision(_))));
+        assert!(matches!(repository.resolve_revision("HEAD:missing.txt"), Err(Error::UnknownRevision(_))));
+        assert!(matches!(repository.resolve_revision(&merged[..3]), Err(Error::UnknownRevision(_))));
+        assert!(matches!(repository.resolve_revision("HEAD~x"), Err(Error::Usage(_))));
+        assert_eq!(repository.log(Some("v1"), None)?.len(), 2);
+        let out = String::from_utf8_lossy(&repository.diff_revisions("HEAD~2", "HEAD", &[] as &[&str], 3)?).into_owned();
+        assert!(out.contains("--- a/1.txt\n+++ b/1.txt\n") && out.contains("new file\n--- /dev/null\n+++ b/3.txt\n"));
+        let out = repository.diff_revisions("HEAD~2", "HEAD", &["test_dir"], 3)?;
+        assert!(out.is_empty());
+        assert_eq!(repository.diff("HEAD~2:1.txt", "HEAD:1.txt", 3)?, b"@@ -1 +1 @@\n-Hello First World!\n\\ No newline at end of file\n+master\n\\ No newline at end of file\n");
+        // 只有一个版本表达式时与工作区比较
+        fs::write(root_path.join("1.txt"), "worktree")?;
+        let out = String::from_utf8_lossy(&repository.diff_revision_worktree("HEAD~2", &[] as &[&str], 3)?).into_owned();
+        assert!(out.contains("-Hello First World!") && out.contains("+worktree") && out.contains("new file\n--- /dev/null\n+++ b/3.txt\n"));
+        assert!(repository.diff_revision_worktree("HEAD", &["test_dir"], 3)?.is_empty());
+        assert_eq!(split_revisions(&repository, root_path, &["HEAD~1", "1.txt"])?, (&["HEAD~1"][..], &["1.txt"][..]));
+        assert_eq!(split_revisions(&repository, root_path, &["HEAD", "dev", "test_dir"])?.0, &["HEAD", "dev"]);
+        assert!(matches!(split_revisions(&repository, root_path, &["HEAD", "missing"]), Err(Error::Usage(_))));
+        // 与分支同名的文件需要用"--"区分
+        fs::write(root_path.join("dev"), "dev")?;
+        assert!(matches!(split_revisions(&repository, root_path, &["dev"]), Err(Error::Usage(_))));
+        assert_eq!(split_revisions(&repository, root_path, &["--", "dev"])?, (&[] as &[&str], &["dev"][..]));
+        fs::remove_file(root_path.join("dev"))?;
+        fs::write(root_path.join("1.txt"), "master")?;
+        repository.checkout("HEAD~1")?;
+        assert_eq!(repository.head()?, Head::Detached(second));
         Ok(())
     }
 
     #[test]
-    fn test_branch()-> Result<(), Box<dyn Error>> {
-        test_commit()?;
-        println!("before create");
-        branch_check()?;
-        branch_create(&"second_branch".to_string())?;
-        println!("after create");
-        branch_check()?;
-        branch_delete(&"second_branch".to_string())?;
-        println!("after delete");
-        branch_check()?;
+    fn test_read_object()-> Result<()> {
+        let repository = test_repository("read-object")?;
+        add_files(&repository)?;
+        let first = repository.commit("master", "first")?;
+        fs::write(repository.root_path().join("1.txt"), "second")?;
+        repository.add(&["1.txt"])?;
+        repository.commit("master", "second")?;
+        let (key, object) = repository.read_object("HEAD~1")?;
+        assert_eq!(key, first);
+        assert!(matches!(object, Object::Commit(commit) if commit.message == "first"));
+        assert!(matches!(repository.read_object("HEAD:test_dir")?.1, Object::Tree(tree) if tree.entries.len() == 1));
+        assert_eq!(repository.read_object("HEAD:1.txt")?.1, Object::Blob(Blob { data: b"second".to_vec() }));
+        assert_eq!(repository.object_size("HEAD~1:1.txt")?, 18);
+        repository.create_annotated_tag("v1", None, "me", "release")?;
+        assert_eq!(repository.read_object("v1")?.1.object_type(), ObjectType::Tag);
+        let out = String::from_utf8_lossy(&repository.diff_commit("v1", 3)?).into_owned();
+        assert!(out.starts_with("diff --minigit a/1.txt b/1.txt\n--- a/1.txt\n+++ b/1.txt\n"));
+        // 根提交与空的tree比较
+        let out = String::from_utf8_lossy(&repository.diff_commit(&first, 3)?).into_owned();
+        assert_eq!(out.matches("new file\n").count(), 2);
         Ok(())
     }
 
     #[test]
-    fn test_checkout()-> Result<(), Box<dyn Error>> {
-        env::set_current_dir(&env::current_dir()?.join("test"))?;
-        let minigit_path = find_minigit(&env::current_dir()?)?;
-        let root_path = minigit_path.parent().unwrap();
-        rm(&vec!["*".to_string()])?;
-        env::set_current_dir(root_path.parent().unwrap())?;
-        test_commit()?;
-        println!("before create");
-        branch_check()?;
-        branch_create(&"second_branch".to_string())?;
-        println!("after create second branch");
-        branch_check()?;
-        fs::write(root_path.join("master.txt"), "This is master branch")?;
-        add(&vec!["master.txt".to_string()])?;
-        commit(&"master".to_string(), &"master commit".to_string())?;
-        checkout(&"second_branch".to_string())?;
-        println!("after checkout new branch");
-        branch_check()?;
-        fs::write(root_path.join("second.txt"), "Test checkout")?;
-        add(&vec!["second.txt".to_string()])?;
-        commit(&"second".to_string(), &"test_second".to_string())?;
-        checkout(&"master".to_string())?;
-        println!("after checkout master branch");
-        branch_check()?;
+    fn test_ls()-> Result<()> {
+        let repository = test_repository("ls")?;
+        add_files(&repository)?;
+        repository.commit("master", "first")?;
+        let paths = |entries: Vec<ListEntry>| entries.into_iter().map(|entry| entry.path).collect::<Vec<_>>();
+        assert_eq!(paths(repository.ls_files(&[] as &[&str])?), vec!["1.txt", "test_dir/2.txt"]);
+        assert_eq!(paths(repository.ls_files(&["test_dir"])?), vec!["test_dir/2.txt"]);
+        let entries = repository.ls_tree("HEAD", None, false)?;
+        assert_eq!(entries.iter().map(|entry| entry.kind).collect::<Vec<_>>(), vec![EntryKind::Blob, EntryKind::Tree]);
+        assert_eq!(entries[0].key, repository.resolve_revision("HEAD:1.txt")?);
+        assert_eq!(paths(repository.ls_tree("HEAD", None, true)?), vec!["1.txt", "test_dir/2.txt"]);
+        assert_eq!(paths(repository.ls_tree("HEAD", Some(Path::new("test_dir")), false)?), vec!["test_dir/2.txt"]);
+        assert_eq!(paths(repository.ls_tree("HEAD", Some(Path::new("1.txt")), false)?), vec!["1.txt"]);
+        assert!(repository.ls_tree("HEAD", Some(Path::new("missing")), false)?.is_empty());
         Ok(())
     }
 
+    #[test]
+    fn test_merge()-> Result<()> {
+        let repository = test_repository("merge")?;
+        let root_path = repository.root_path();
+        fs::write(root_path.join("test_merge.txt"), "my\nfirst\ntest\nmerge\nin\nbranch\n")?;
+        fs::write(root_path.join("common.txt"), "common")?;
+        repository.add(&[root_path])?;
+        repository.commit("master", "first commit")?;
+        repository.create_branch("second_branch")?;
+        fs::write(root_path.join("test_merge.txt"), "my\nfirst\ntest\nmerge\nin\nbranch\nmaster\n")?;
+        fs::write(root_path.join("master.txt"), "master")?;
+        repository.add(&[root_path])?;
+        repository.commit("master", "master_commmit")?;
+        repository.checkout("second_branch")?;
+        fs::write(root_path.join("test_merge.txt"), "my\nfirst\ntest\nmerge\nin\nbranch\nsecond branch\n")?;
+        fs::write(root_path.join("second.txt"), "second")?;
+        repository.add(&[root_path])?;
+        let second_key = repository.commit("second branch", "second_commit")?;
+        // 暂存区中有修改时不能合并，与合并无关的没有暂存的修改保留下来
+        fs::write(root_path.join("common.txt"), "not committed")?;
+        repository.add(&["common.txt"])?;
+        assert!(matches!(repository.merge("master", "second branch"), Err(Error::WouldOverwrite(paths)) if paths == vec![root_path.join("common.txt")]));
+        repository.restore(None, true, &["common.txt"])?;
+        let modified = fs::metadata(root_path.join("second.txt"))?.modified()?;
+        let re = repository.merge("master", "second branch")?;
+        assert_eq!(re, MergeResult::Conflict(vec![root_path.join("test_merge.txt")]));
+        // 合并前后相同的文件不会被重写
+        assert_eq!(fs::metadata(root_path.join("second.txt"))?.modified()?, modified);
+        assert_eq!(fs::read_to_string(root_path.join("common.txt"))?, "not committed");
+        assert!(root_path.join("master.txt").is_file() && root_path.join("second.txt").is_file());
+        // 没有冲突的修改已经暂存，不是未跟踪的文件
+        let status = repository.status()?;
+        assert!(status.untracked.is_empty());
+        assert!(status.unstaged.contains(&("common.txt".to_string(), FileStatus::Modified)));
+        // 冲突的文件没有被标记为已解决之前不能提交
+        assert_eq!(status.unmerged, vec!["test_merge.txt"]);
+        assert!(status.staged.iter().all(|(name, _)| name != "test_merge.txt"));
+        assert!(matches!(repository.commit("second branch", "resolve conflict"), Err(Error::MergeConflict(paths)) if paths == vec![root_path.join("test_merge.txt")]));
+        fs::write(root_path.join("test_merge.txt"), "my\nfirst\ntest\nmerge\nin\nbranch\nboth\n")?;
+        assert!(repository.status()?.unstaged.iter().all(|(name, _)| name != "test_merge.txt"));
+        // 只暂存解决了冲突的文件
+        repository.add(&["test_merge.txt"])?;
+        let status = repository.status()?;
+        assert!(status.unmerged.is_empty());
+        assert!(status.staged.contains(&("test_merge.txt".to_string(), FileStatus::Modified)));
+        repository.commit("second branch", "resolve conflict")?;
+        assert_eq!(repository.log(None, Some(1))?[0].parents.len(), 2);
+        let files = repository.ls_tree("HEAD", None, true)?.into_iter().map(|entry| entry.path).collect::<Vec<_>>();
+        assert_eq!(files, vec!["common.txt", "master.txt", "second.txt", "test_merge.txt"]);
+        assert_eq!(repository.merge("master", "second branch")?, MergeResult::UpToDate);
+        repository.checkout("master")?;
+        assert!(matches!(repository.merge("second_branch", "master")?, MergeResult::FastForward(_)));
+        assert!(root_path.join("second.txt").is_file());
+        assert_ne!(repository.log(None, Some(1))?[0].key, second_key);
+        assert_eq!(fs::read_to_string(root_path.join("common.txt"))?, "not committed");
+        Ok(())
+    }
 
     #[test]
-    fn test_merge()-> Result<(), Box<dyn Error>> {
-        test_commit()?;
-        let root_path = env::current_dir()?;
-        println!("root path = {}", root_path.display());
-        println!("before create");
-        branch_check()?;
-        branch_create(&"second_branch".to_string())?;
-        println!("after create");
-        branch_check()?;
-        fs::write(&root_path.join("test_merge.txt"), &"my\nfirst\ntest\nmerge\nin\nbranch\nmaster".as_bytes())?;
-        fs::create_dir_all(&root_path.join("test_master"))?;
-        add(&vec!["*".to_string()])?;
-        commit(&"master".to_string(), &"master_commmit".to_string())?;
-        checkout(&"second_branch".to_string())?;
-        println!("after checkout");
-        branch_check()?;
-        fs::write(&root_path.join("test_merge.txt"), &"my\nfirst\ntest\nmerge\nin\nbranch\nsecond branch".as_bytes())?;
-        fs::create_dir_all(&root_path.join("test_second"))?;
-        add(&vec!["*".to_string()])?;
-        commit(&"second branch".to_string(), &"second_commit".to_string())?;
-        println!("ready to merge");
-        merge(&"master".to_string())?;
+    fn test_merge_bases()-> Result<()> {
+        let repository = test_repository("merge-bases")?;
+        let root_path = repository.root_path();
+        let commit_file = |name: &str| -> Result<String> {
+            fs::write(root_path.join(name), name)?;
+            repository.add(&[name])?;
+            repository.commit("me", name)
+        };
+        let root = commit_file("root.txt")?;
+        repository.create_branch("other")?;
+        let ours = commit_file("ours.txt")?;
+        repository.checkout("other")?;
+        let theirs = commit_file("theirs.txt")?;
+        assert_eq!(repository.find_merge_bases(&ours, &theirs)?, vec![root.clone()]);
+        assert_eq!(repository.find_merge_bases(&ours, &root)?, vec![root.clone()]);
+        // 交叉合并：两个分支各自合并了对方原来的提交，之后它们有两个最近公共祖先
+        let MergeResult::Merged(merge2) = repository.merge(&ours, "me")? else { panic!("expected merge commit") };
+        repository.checkout("master")?;
+        let MergeResult::Merged(merge1) = repository.merge(&theirs, "me")? else { panic!("expected merge commit") };
+        let mut bases = repository.find_merge_bases(&merge1, &merge2)?;
+        bases.sort();
+        let mut expected = vec![ours, theirs];
+        expected.sort();
+        assert_eq!(bases, expected);
+        commit_file("new.txt")?;
+        assert!(matches!(repository.merge("other", "me")?, MergeResult::Merged(_)));
+        assert_eq!(repository.ls_tree("HEAD", None, false)?.len(), 4);
+        // 两个公共祖先中的文件不同：x.txt在ours中修改过，在theirs中没有，只以theirs为基准时会得到冲突
+        let commit_content = |name: &str, content: &str| -> Result<String> {
+            fs::write(root_path.join(name), content)?;
+            repository.add(&[name])?;
+            repository.commit("me", content)
+        };
+        commit_content("x.txt", "1")?;
+        repository.create_branch("cross")?;
+        let ours = commit_content("x.txt", "2")?;
+        repository.checkout("cross")?;
+        let theirs = commit_content("y.txt", "y")?;
+        assert!(matches!(repository.merge(&ours, "me")?, MergeResult::Merged(_)));
+        repository.checkout("master")?;
+        assert!(matches!(repository.merge(&theirs, "me")?, MergeResult::Merged(_)));
+        let head = commit_content("x.txt", "3")?;
+        assert_eq!(repository.find_merge_bases(&head, &repository.resolve_revision("cross")?)?.len(), 2);
+        // 先合并两个公共祖先，以合并的结果为基准，结果与公共祖先的顺序无关
+        assert!(matches!(repository.merge("cross", "me")?, MergeResult::Merged(_)));
+        assert_eq!(fs::read_to_string(root_path.join("x.txt"))?, "3");
+        assert_eq!(fs::read_to_string(root_path.join("y.txt"))?, "y");
+        assert!(repository.status()?.unmerged.is_empty());
         Ok(())
     }
 
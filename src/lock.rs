@@ -0,0 +1,174 @@
+//! minigit 文件锁：修改index和引用时先创建'<文件>.lock'，新内容写入临时文件'.<文件>.lock'，写完后再重命名为原文件
+
+use std::ffi::OsString;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime};
+
+use crate::error::{Error, Result};
+
+/**
+ * 无法判断持有者进程是否存在时，超过这个时间没有修改的锁被视为残留的锁
+ */
+const STALE_LOCK_AGE: Duration = Duration::from_secs(600);
+
+/**
+ * 对文件path的独占锁，持有期间其他minigit进程无法修改这个文件
+ * - 'acquire'以独占方式创建'<path>.lock'，锁文件中记录持有者的进程号，直到释放锁都不会改变
+ * - 'write_all'将新的内容写入临时文件'.<文件名>.lock'，'commit'再将它重命名为path，因此path要么是旧内容要么是完整的新内容
+ * - 没有'commit'就被丢弃时删除临时文件和锁文件，path保持不变
+ *
+ * 分支和标签的名字不能以'.'开头，所以临时文件不会与其他文件的锁文件重名，并且和锁文件一样不会被当作分支或者标签
+ */
+#[derive(Debug)]
+pub struct LockFile {
+    path: PathBuf,
+    lock_path: PathBuf,
+    temp_path: PathBuf,
+    temp: Option<File>,
+    committed: bool,
+}
+
+fn create_lock(lock_path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(lock_path)
+}
+
+/**
+ * 两次读取的元数据是否属于同一个没有被修改过的文件
+ */
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if (a.dev(), a.ino()) != (b.dev(), b.ino()) {
+            return false;
+        }
+    }
+    a.len() == b.len() && a.modified().ok() == b.modified().ok()
+}
+
+/**
+ * 锁文件中记录的进程已经不存在，或者锁文件很久没有修改时，认为是崩溃的进程留下的锁
+ */
+fn is_stale(lock_path: &Path) -> bool {
+    let pid = fs::read_to_string(lock_path).ok()
+        .and_then(|value| value.strip_prefix("pid ")?.trim().parse::<u32>().ok());
+    // 只有存在/proc时才能检查进程是否存在
+    let proc_path = Path::new("/proc");
+    if let Some(pid) = pid {
+        if proc_path.join("self").exists() {
+            return !proc_path.join(pid.to_string()).exists();
+        }
+    }
+    fs::metadata(lock_path).and_then(|metadata| metadata.modified()).ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > STALE_LOCK_AGE)
+}
+
+impl LockFile {
+    /**
+     * 'acquire'获取文件path的锁，锁已经被其他进程持有时返回Error::Locked
+     */
+    pub fn acquire(path: &Path) -> Result<LockFile> {
+        let mut lock_path = path.as_os_str().to_os_string();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
+        let file = match create_lock(&lock_path) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                // 检查前后锁文件不变时才删除，否则可能删掉其他进程刚刚清理残留的锁之后重新创建的锁
+                let examined = fs::symlink_metadata(&lock_path);
+                let stale = is_stale(&lock_path);
+                match (examined, fs::symlink_metadata(&lock_path)) {
+                    (Ok(examined), Ok(current)) if stale && same_file(&examined, &current) => {
+                        fs::remove_file(&lock_path)?;
+                        create_lock(&lock_path)
+                    },
+                    _ => Err(err),
+                }
+            },
+            other => other,
+        };
+        let mut file = file.map_err(|err| match err.kind() {
+            io::ErrorKind::AlreadyExists => Error::Locked(lock_path.clone()),
+            _ => Error::Io(err),
+        })?;
+        let mut temp_name = OsString::from(".");
+        temp_name.push(path.file_name().unwrap_or_default());
+        temp_name.push(".lock");
+        let lock = LockFile { path: path.to_path_buf(), lock_path, temp_path: path.with_file_name(temp_name), temp: None, committed: false };
+        file.write_all(format!("pid {}\n", process::id()).as_bytes())?;
+        Ok(lock)
+    }
+
+    /**
+     * 'write_all'用data替换临时文件的内容，在'commit'之前不会影响原文件
+     */
+    pub fn write_all(&mut self, data: &[u8]) -> Result<()> {
+        let mut temp = File::create(&self.temp_path)?;
+        temp.write_all(data)?;
+        self.temp = Some(temp);
+        Ok(())
+    }
+
+    /**
+     * 'commit'将临时文件重命名为原文件并释放锁，没有写入过内容时原文件变为空文件
+     */
+    pub fn commit(mut self) -> Result<()> {
+        let temp = match self.temp.take() {
+            Some(temp) => temp,
+            None => File::create(&self.temp_path)?,
+        };
+        temp.sync_all()?;
+        fs::rename(&self.temp_path, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+        let _ = fs::remove_file(&self.lock_path);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_lock() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("minigit-lock-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("HEAD");
+        fs::write(&path, "master")?;
+        let mut lock = LockFile::acquire(&path)?;
+        assert!(matches!(LockFile::acquire(&path), Err(Error::Locked(_))));
+        lock.write_all(b"second")?;
+        assert_eq!(fs::read_to_string(&path)?, "master");
+        // 写入新内容后锁文件中仍然是持有者的进程号，持有者还在时锁不会被当作残留的锁
+        assert_eq!(fs::read_to_string(dir.join("HEAD.lock"))?, format!("pid {}\n", process::id()));
+        assert!(matches!(LockFile::acquire(&path), Err(Error::Locked(_))));
+        lock.commit()?;
+        assert_eq!(fs::read_to_string(&path)?, "second");
+        assert!(!dir.join("HEAD.lock").exists() && !dir.join(".HEAD.lock").exists());
+        // 没有commit的锁被丢弃时不修改原文件
+        let mut lock = LockFile::acquire(&path)?;
+        lock.write_all(b"third")?;
+        drop(lock);
+        assert_eq!(fs::read_to_string(&path)?, "second");
+        assert!(!dir.join("HEAD.lock").exists() && !dir.join(".HEAD.lock").exists());
+        // 持有者进程已经不存在的锁可以被清理
+        if Path::new("/proc/self").exists() {
+            fs::write(dir.join("HEAD.lock"), format!("pid {}\n", u32::MAX))?;
+            LockFile::acquire(&path)?;
+        }
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,796 @@
+//! minigit 对象模型：blob、tree、commit、tag 四种对象在两种保存格式下的解析与序列化
+
+use std::ffi::OsString;
+use std::io::Read;
+use flate2::{Compression, read::ZlibEncoder, read::ZlibDecoder};
+use chrono::{DateTime, NaiveDateTime};
+
+use crate::error::{Error, Result};
+use crate::hash::HashAlgorithm;
+
+/**
+ * 对象在objects中的保存格式，在'init'时选择
+ * - Minigit：对象内容为 "类型\0" 加上内容，blob的内容使用zlib压缩，对象本身不压缩
+ * - Git：与git相同，对象内容为 "类型 长度\0" 加上内容，计算key之后整个对象使用zlib压缩，可以使用git的工具查看
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ObjectFormat {
+    #[default]
+    Minigit,
+    Git,
+}
+
+impl ObjectFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectFormat::Minigit => "minigit",
+            ObjectFormat::Git => "git",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ObjectFormat> {
+        match name {
+            "minigit" => Some(ObjectFormat::Minigit),
+            "git" => Some(ObjectFormat::Git),
+            _ => None,
+        }
+    }
+
+    /**
+     * 'store'将对象内容转化为写入objects文件的数据
+     */
+    pub fn store(&self, value: &[u8]) -> Vec<u8> {
+        match self {
+            ObjectFormat::Minigit => value.to_vec(),
+            ObjectFormat::Git => {
+                let mut data = Vec::new();
+                ZlibEncoder::new(value, Compression::default()).read_to_end(&mut data).expect("compress in-memory data can't fail");
+                data
+            },
+        }
+    }
+
+    /**
+     * 'load'将objects文件中的数据还原为对象内容
+     */
+    pub fn load(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            ObjectFormat::Minigit => Ok(data),
+            ObjectFormat::Git => {
+                let mut value = Vec::new();
+                ZlibDecoder::new(&data[..]).read_to_end(&mut value)
+                                           .map_err(|err| Error::corrupt(format!("corrupt zlib data: {err}")))?;
+                Ok(value)
+            },
+        }
+    }
+
+    fn header(&self, object_type: ObjectType, len: usize) -> Vec<u8> {
+        match self {
+            ObjectFormat::Minigit => format!("{}\0", object_type.as_str()).into_bytes(),
+            ObjectFormat::Git => format!("{} {len}\0", object_type.as_str()).into_bytes(),
+        }
+    }
+}
+
+/**
+ * 对象的类型，对应对象内容开头的类型头
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ObjectType {
+    Blob,
+    Tree,
+    Commit,
+    Tag,
+}
+
+impl ObjectType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectType::Blob => "blob",
+            ObjectType::Tree => "tree",
+            ObjectType::Commit => "commit",
+            ObjectType::Tag => "tag",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ObjectType> {
+        match name {
+            "blob" => Some(ObjectType::Blob),
+            "tree" => Some(ObjectType::Tree),
+            "commit" => Some(ObjectType::Commit),
+            "tag" => Some(ObjectType::Tag),
+            _ => None,
+        }
+    }
+
+    /**
+     * 'of'读取对象内容开头的类型头，返回对象类型和类型头之后的内容
+     */
+    pub fn of(format: ObjectFormat, value: &[u8]) -> Result<(ObjectType, &[u8])> {
+        let end = match value.iter().position(|&b| b == b'\0') {
+            None => return Err(Error::corrupt("missing object header")),
+            Some(end) => end,
+        };
+        let (header, body) = (&value[..end], &value[(end + 1)..]);
+        let name = match format {
+            ObjectFormat::Minigit => header,
+            ObjectFormat::Git => {
+                let (name, len) = match header.iter().position(|&b| b == b' ') {
+                    None => return Err(Error::corrupt("missing object length")),
+                    Some(i) => (&header[..i], &header[(i + 1)..]),
+                };
+                if String::from_utf8_lossy(len).parse::<usize>().ok() != Some(body.len()) {
+                    return Err(Error::corrupt(format!("object length {:?} doesn't match content", String::from_utf8_lossy(len))));
+                }
+                name
+            },
+        };
+        let name = String::from_utf8_lossy(name);
+        let object_type = ObjectType::from_name(&name).ok_or_else(|| Error::corrupt(format!("unknown object type {name:?}")))?;
+        Ok((object_type, body))
+    }
+}
+
+fn expect_header(format: ObjectFormat, value: &[u8], object_type: ObjectType) -> Result<&[u8]> {
+    let (found, body) = ObjectType::of(format, value)?;
+    if found != object_type {
+        return Err(Error::corrupt(format!("expected {} but object type is {}", object_type.as_str(), found.as_str())));
+    }
+    Ok(body)
+}
+
+/**
+ * 检查key是否为任意一种哈希算法得到的合法key（小写十六进制字符串）
+ */
+pub fn is_valid_key(key: &str) -> bool {
+    HashAlgorithm::from_key_len(key.len()).is_some_and(|hash| hash.is_valid_key(key))
+}
+
+fn parse_key(key: &[u8], object_type: ObjectType) -> Result<String> {
+    let key = String::from_utf8_lossy(key).into_owned();
+    if !is_valid_key(&key) {
+        return Err(Error::corrupt(format!("invaild object key {:?} in {}", key, object_type.as_str())));
+    }
+    Ok(key)
+}
+
+pub(crate) fn hex_to_bytes(key: &str) -> Vec<u8> {
+    (0..key.len()).step_by(2).map(|i| u8::from_str_radix(&key[i..(i + 2)], 16).unwrap_or(0)).collect()
+}
+
+pub(crate) fn bytes_to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/**
+ * 'bytes_to_os_string'还原对象和index中保存的文件名，数据可能来自git仓库或者已经损坏，不能假设是合法的编码
+ * unix上文件名可以是任意字节；其他系统上只接受UTF-8，不是UTF-8时返回None，由调用者报告数据损坏
+ */
+pub(crate) fn bytes_to_os_string(bytes: &[u8]) -> Option<OsString> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(std::ffi::OsStr::from_bytes(bytes).to_os_string())
+    }
+    #[cfg(not(unix))]
+    {
+        String::from_utf8(bytes.to_vec()).ok().map(OsString::from)
+    }
+}
+
+
+/**
+ * 文件对象，data为文件的原始内容，Minigit格式序列化时使用zlib压缩
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Blob {
+    pub data: Vec<u8>,
+}
+
+impl Blob {
+    pub fn parse(format: ObjectFormat, value: &[u8]) -> Result<Blob> {
+        let body = expect_header(format, value, ObjectType::Blob)?;
+        if format == ObjectFormat::Git {
+            return Ok(Blob { data: body.to_vec() });
+        }
+        let mut data = Vec::new();
+        ZlibDecoder::new(body).read_to_end(&mut data)
+                              .map_err(|err| Error::corrupt(format!("corrupt zlib data: {err}")))?;
+        Ok(Blob { data })
+    }
+
+    pub fn serialize(&self, format: ObjectFormat) -> Vec<u8> {
+        Blob::serialize_from(format, &self.data[..]).expect("compress in-memory data can't fail")
+    }
+
+    /**
+     * 'serialize_from'从reader中读取文件内容并直接生成blob对象，Minigit格式时避免先把整个文件读入内存再压缩
+     */
+    pub fn serialize_from(format: ObjectFormat, mut reader: impl Read) -> std::io::Result<Vec<u8>> {
+        match format {
+            ObjectFormat::Minigit => {
+                let mut value = format.header(ObjectType::Blob, 0);
+                ZlibEncoder::new(reader, Compression::fast()).read_to_end(&mut value)?;
+                Ok(value)
+            },
+            ObjectFormat::Git => {
+                // 类型头中需要内容的长度，所以只能先读出全部内容
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data)?;
+                let mut value = format.header(ObjectType::Blob, data.len());
+                value.append(&mut data);
+                Ok(value)
+            },
+        }
+    }
+}
+
+
+/**
+ * tree中一项的类型，也就是它的文件模式
+ * - Blob为普通文件，Executable为可执行文件，两者都保存文件内容
+ * - Symlink为符号链接，它的blob对象中保存链接指向的路径
+ * - Tree为文件夹
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EntryKind {
+    Blob,
+    Tree,
+    Symlink,
+    Executable,
+}
+
+impl EntryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntryKind::Blob => "blob",
+            EntryKind::Tree => "tree",
+            EntryKind::Symlink => "symlink",
+            EntryKind::Executable => "executable",
+        }
+    }
+
+    /**
+     * 'is_file'检查这一项是否为普通文件或者可执行文件，它们的内容可以逐行比较和合并
+     */
+    pub fn is_file(&self) -> bool {
+        matches!(self, EntryKind::Blob | EntryKind::Executable)
+    }
+
+    /**
+     * 'object_type'返回这一项指向的对象的类型，只有文件夹指向tree，其他都指向blob
+     */
+    pub fn object_type(&self) -> ObjectType {
+        match self {
+            EntryKind::Tree => ObjectType::Tree,
+            _ => ObjectType::Blob,
+        }
+    }
+
+    /**
+     * 'git_mode'返回Git格式中这一项的文件模式
+     */
+    pub fn git_mode(&self) -> &'static str {
+        match self {
+            EntryKind::Blob => "100644",
+            EntryKind::Tree => "40000",
+            EntryKind::Symlink => "120000",
+            EntryKind::Executable => "100755",
+        }
+    }
+}
+
+/**
+ * tree中的一项，对应文件夹中的一个文件或子文件夹
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    pub kind: EntryKind,
+    pub key: String,
+    pub name: OsString,
+}
+
+/**
+ * 文件夹对象
+ * - Minigit格式：每一项序列化为 "类型 key 名字\0"，按照名字排序
+ * - Git格式：每一项序列化为 "模式 名字\0" 加上二进制的key，按照git的规则排序（文件夹的名字后面视为有'/'）
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Tree {
+    pub entries: Vec<TreeEntry>,
+}
+
+impl Tree {
+    /**
+     * 'parse'解析tree对象，Git格式中key为二进制，需要根据hash确定key的长度
+     */
+    pub fn parse(format: ObjectFormat, hash: HashAlgorithm, value: &[u8]) -> Result<Tree> {
+        let body = expect_header(format, value, ObjectType::Tree)?;
+        match format {
+            ObjectFormat::Minigit => Tree::parse_minigit(body),
+            ObjectFormat::Git => Tree::parse_git(body, hash.hex_len() / 2),
+        }
+    }
+
+    fn parse_minigit(body: &[u8]) -> Result<Tree> {
+        if !body.is_empty() && !body.ends_with(b"\0") {
+            return Err(Error::corrupt("tree entry isn't terminated"));
+        }
+        let mut entries = Vec::new();
+        for entry in body.split(|&b| b == b'\0').filter(|entry| !entry.is_empty()) {
+            let mut it = entry.splitn(3, |&b| b == b' ');
+            let (kind, key, name) = match (it.next(), it.next(), it.next()) {
+                (Some(kind), Some(key), Some(name)) if !name.is_empty() => (kind, key, name),
+                _ => return Err(Error::corrupt(format!("invaild tree entry {:?}", String::from_utf8_lossy(entry)))),
+            };
+            let kind = match kind {
+                b"blob" => EntryKind::Blob,
+                b"tree" => EntryKind::Tree,
+                b"symlink" => EntryKind::Symlink,
+                b"executable" => EntryKind::Executable,
+                other => return Err(Error::corrupt(format!("unknown tree entry type {:?}", String::from_utf8_lossy(other)))),
+            };
+            let key = parse_key(key, ObjectType::Tree)?;
+            let name = bytes_to_os_string(name)
+                .ok_or_else(|| Error::corrupt(format!("invaild tree entry name {:?}", String::from_utf8_lossy(name))))?;
+            entries.push(TreeEntry { kind, key, name });
+        }
+        Ok(Tree { entries })
+    }
+
+    fn parse_git(mut body: &[u8], key_len: usize) -> Result<Tree> {
+        let mut entries = Vec::new();
+        while !body.is_empty() {
+            let end = match body.iter().position(|&b| b == b'\0') {
+                Some(end) if body.len() >= end + 1 + key_len => end,
+                _ => return Err(Error::corrupt("tree entry isn't terminated")),
+            };
+            let (mode, name) = match body[..end].iter().position(|&b| b == b' ') {
+                Some(i) if i + 1 < end => (&body[..i], &body[(i + 1)..end]),
+                _ => return Err(Error::corrupt(format!("invaild tree entry {:?}", String::from_utf8_lossy(&body[..end])))),
+            };
+            let kind = match mode {
+                b"100644" => EntryKind::Blob,
+                b"100755" => EntryKind::Executable,
+                b"40000" => EntryKind::Tree,
+                b"120000" => EntryKind::Symlink,
+                other => return Err(Error::corrupt(format!("unsupported tree entry mode {:?}", String::from_utf8_lossy(other)))),
+            };
+            let key = bytes_to_hex(&body[(end + 1)..(end + 1 + key_len)]);
+            let name = bytes_to_os_string(name)
+                .ok_or_else(|| Error::corrupt(format!("invaild tree entry name {:?}", String::from_utf8_lossy(name))))?;
+            entries.push(TreeEntry { kind, key, name });
+            body = &body[(end + 1 + key_len)..];
+        }
+        Ok(Tree { entries })
+    }
+
+    /**
+     * 'serialize'序列化时对每一项排序，保证相同内容的文件夹得到相同的key
+     */
+    pub fn serialize(&self, format: ObjectFormat) -> Vec<u8> {
+        let mut entries = self.entries.iter().collect::<Vec<&TreeEntry>>();
+        let mut body = Vec::new();
+        match format {
+            ObjectFormat::Minigit => {
+                entries.sort_by(|x, y| x.name.cmp(&y.name).then(x.kind.cmp(&y.kind)));
+                for entry in entries {
+                    body.extend_from_slice(format!("{} {} ", entry.kind.as_str(), entry.key).as_bytes());
+                    body.extend_from_slice(entry.name.as_encoded_bytes());
+                    body.push(b'\0');
+                }
+            },
+            ObjectFormat::Git => {
+                let sort_name = |entry: &TreeEntry| {
+                    let mut name = entry.name.as_encoded_bytes().to_vec();
+                    if entry.kind == EntryKind::Tree {
+                        name.push(b'/');
+                    }
+                    name
+                };
+                entries.sort_by_cached_key(|entry| sort_name(entry));
+                for entry in entries {
+                    body.extend_from_slice(format!("{} ", entry.kind.git_mode()).as_bytes());
+                    body.extend_from_slice(entry.name.as_encoded_bytes());
+                    body.push(b'\0');
+                    body.append(&mut hex_to_bytes(&entry.key));
+                }
+            },
+        }
+        let mut value = format.header(ObjectType::Tree, body.len());
+        value.append(&mut body);
+        value
+    }
+
+    pub fn get(&self, name: &OsString, kind: EntryKind) -> Option<&TreeEntry> {
+        self.entries.iter().find(|entry| entry.name == *name && entry.kind == kind)
+    }
+}
+
+
+/**
+ * 提交对象，Minigit格式序列化为
+ * ```text
+ * commit\0parent <key>        （零个或多个）
+ * author <author>
+ * datetime <datetime>
+ * note <message>             （提交信息可以有多行）
+ * tree <key>
+ * ```
+ * tree为EMPTY_TREE时表示提交时暂存区为空
+ *
+ * Git格式序列化为git的提交格式，datetime保存为时间戳，作者没有邮箱时使用空邮箱
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Commit {
+    pub parents: Vec<String>,
+    pub author: String,
+    pub datetime: String,
+    pub message: String,
+    pub tree: String,
+}
+
+impl Commit {
+    pub const EMPTY_TREE: &'static str = "\0";
+
+    pub fn parse(format: ObjectFormat, value: &[u8]) -> Result<Commit> {
+        let body = expect_header(format, value, ObjectType::Commit)?;
+        let body = String::from_utf8(body.to_vec()).map_err(|_| Error::corrupt("commit isn't utf-8"))?;
+        match format {
+            ObjectFormat::Minigit => Commit::parse_minigit(&body),
+            ObjectFormat::Git => Commit::parse_git(&body),
+        }
+    }
+
+    fn parse_minigit(body: &str) -> Result<Commit> {
+        let invaild = |field: &str| Error::corrupt(format!("missing {field} header in commit"));
+        let mut rest = body;
+        let mut parents = Vec::new();
+        while let Some(line) = rest.strip_prefix("parent ") {
+            let (parent, next) = line.split_once('\n').ok_or_else(|| invaild("author"))?;
+            // 旧版本的根提交使用"parent \0"
+            if parent != "\0" {
+                parents.push(parse_key(parent.as_bytes(), ObjectType::Commit)?);
+            }
+            rest = next;
+        }
+        let (author, rest) = rest.split_once('\n').ok_or_else(|| invaild("author"))?;
+        let author = author.strip_prefix("author ").ok_or_else(|| invaild("author"))?;
+        let (datetime, rest) = rest.split_once('\n').ok_or_else(|| invaild("datetime"))?;
+        let datetime = datetime.strip_prefix("datetime ").ok_or_else(|| invaild("datetime"))?;
+        let (note, tree) = rest.rsplit_once('\n').ok_or_else(|| invaild("tree"))?;
+        let message = note.strip_prefix("note ").ok_or_else(|| invaild("note"))?;
+        let tree = tree.strip_prefix("tree ").ok_or_else(|| invaild("tree"))?;
+        let tree = if tree == Commit::EMPTY_TREE { tree.to_string() } else { parse_key(tree.as_bytes(), ObjectType::Commit)? };
+        Ok(Commit {
+            parents,
+            author: author.to_string(),
+            datetime: datetime.to_string(),
+            message: message.to_string(),
+            tree,
+        })
+    }
+
+    fn parse_git(body: &str) -> Result<Commit> {
+        let invaild = |field: &str| Error::corrupt(format!("missing {field} header in commit"));
+        let (headers, message) = body.split_once("\n\n").ok_or_else(|| invaild("message"))?;
+        let mut commit = Commit {
+            message: message.strip_suffix('\n').unwrap_or(message).to_string(),
+            ..Default::default()
+        };
+        for line in headers.lines() {
+            match line.split_once(' ') {
+                Some(("tree", key)) => commit.tree = parse_key(key.as_bytes(), ObjectType::Commit)?,
+                Some(("parent", key)) => commit.parents.push(parse_key(key.as_bytes(), ObjectType::Commit)?),
+                Some(("author", signature)) => {
+                    (commit.author, commit.datetime) = parse_signature(signature).ok_or_else(|| invaild("author"))?;
+                },
+                // committer等其他头部不需要保存
+                _ => {},
+            }
+        }
+        if commit.tree.is_empty() {
+            return Err(invaild("tree"));
+        }
+        Ok(commit)
+    }
+
+    pub fn serialize(&self, format: ObjectFormat) -> Vec<u8> {
+        let mut body = String::new();
+        match format {
+            ObjectFormat::Minigit => {
+                for parent in &self.parents {
+                    body.push_str(&format!("parent {parent}\n"));
+                }
+                body.push_str(&format!("author {}\ndatetime {}\nnote {}\ntree {}", self.author, self.datetime, self.message, self.tree));
+            },
+            ObjectFormat::Git => {
+                body.push_str(&format!("tree {}\n", self.tree));
+                for parent in &self.parents {
+                    body.push_str(&format!("parent {parent}\n"));
+                }
+                let signature = format_signature(&self.author, &self.datetime);
+                body.push_str(&format!("author {signature}\ncommitter {signature}\n\n{}\n", self.message));
+            },
+        }
+        let mut value = format.header(ObjectType::Commit, body.len());
+        value.append(&mut body.into_bytes());
+        value
+    }
+}
+
+
+/**
+ * 解析提交和标签中保存的datetime（UTC时间），无法解析时返回None
+ */
+pub(crate) fn parse_datetime(datetime: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M:%S%.f UTC").ok()
+}
+
+/**
+ * git格式中作者和标签创建者的签名 "名字 <邮箱> 时间戳 时区"，返回名字（没有邮箱时去掉空邮箱）和datetime
+ */
+fn parse_signature(signature: &str) -> Option<(String, String)> {
+    let mut it = signature.rsplitn(3, ' ');
+    let (timestamp, name) = match (it.next(), it.next(), it.next()) {
+        (Some(_), Some(timestamp), Some(name)) => (timestamp, name),
+        _ => return None,
+    };
+    let datetime = DateTime::from_timestamp(timestamp.parse::<i64>().ok()?, 0)?;
+    Some((name.strip_suffix(" <>").unwrap_or(name).to_string(), datetime.to_string()))
+}
+
+fn format_signature(name: &str, datetime: &str) -> String {
+    let timestamp = parse_datetime(datetime).map(|datetime| datetime.and_utc().timestamp()).unwrap_or(0);
+    if name.contains('<') { format!("{name} {timestamp} +0000") } else { format!("{name} <> {timestamp} +0000") }
+}
+
+/**
+ * 附注标签对象，Minigit格式序列化为
+ * ```text
+ * tag\0object <key>
+ * type <对象类型>
+ * tag <标签名字>
+ * tagger <tagger>
+ * datetime <datetime>
+ * note <message>             （标签信息可以有多行）
+ * ```
+ * Git格式序列化为git的标签格式
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub object: String,
+    pub object_type: ObjectType,
+    pub name: String,
+    pub tagger: String,
+    pub datetime: String,
+    pub message: String,
+}
+
+impl Tag {
+    pub fn parse(format: ObjectFormat, value: &[u8]) -> Result<Tag> {
+        let body = expect_header(format, value, ObjectType::Tag)?;
+        let body = String::from_utf8(body.to_vec()).map_err(|_| Error::corrupt("tag isn't utf-8"))?;
+        let invaild = |field: &str| Error::corrupt(format!("missing {field} header in tag"));
+        let (headers, message) = match format {
+            ObjectFormat::Minigit => {
+                let (headers, note) = body.split_once("\nnote ").ok_or_else(|| invaild("note"))?;
+                (headers, note)
+            },
+            ObjectFormat::Git => {
+                let (headers, message) = body.split_once("\n\n").ok_or_else(|| invaild("message"))?;
+                (headers, message.strip_suffix('\n').unwrap_or(message))
+            },
+        };
+        let (mut object, mut object_type, mut name, mut tagger, mut datetime) = (None, None, None, None, None);
+        for line in headers.lines() {
+            match line.split_once(' ') {
+                Some(("object", key)) => object = Some(key),
+                Some(("type", value)) => object_type = Some(ObjectType::from_name(value).ok_or_else(|| invaild("type"))?),
+                Some(("tag", value)) => name = Some(value.to_string()),
+                Some(("tagger", value)) if format == ObjectFormat::Git => {
+                    let (who, when) = parse_signature(value).ok_or_else(|| invaild("tagger"))?;
+                    (tagger, datetime) = (Some(who), Some(when));
+                },
+                Some(("tagger", value)) => tagger = Some(value.to_string()),
+                Some(("datetime", value)) => datetime = Some(value.to_string()),
+                _ => {},
+            }
+        }
+        Ok(Tag {
+            object: parse_key(object.ok_or_else(|| invaild("object"))?.as_bytes(), ObjectType::Tag)?,
+            object_type: object_type.ok_or_else(|| invaild("type"))?,
+            name: name.ok_or_else(|| invaild("tag"))?,
+            tagger: tagger.ok_or_else(|| invaild("tagger"))?,
+            datetime: datetime.ok_or_else(|| invaild("datetime"))?,
+            message: message.to_string(),
+        })
+    }
+
+    pub fn serialize(&self, format: ObjectFormat) -> Vec<u8> {
+        let body = match format {
+            ObjectFormat::Minigit => format!("object {}\ntype {}\ntag {}\ntagger {}\ndatetime {}\nnote {}",
+                                             self.object, self.object_type.as_str(), self.name, self.tagger, self.datetime, self.message),
+            ObjectFormat::Git => format!("object {}\ntype {}\ntag {}\ntagger {}\n\n{}\n",
+                                         self.object, self.object_type.as_str(), self.name,
+                                         format_signature(&self.tagger, &self.datetime), self.message),
+        };
+        let mut value = format.header(ObjectType::Tag, body.len());
+        value.append(&mut body.into_bytes());
+        value
+    }
+}
+
+
+
+/**
+ * 任意一种对象，'parse'根据对象内容开头的类型头选择解析方法
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Object {
+    Blob(Blob),
+    Tree(Tree),
+    Commit(Commit),
+    Tag(Tag),
+}
+
+impl Object {
+    pub fn parse(format: ObjectFormat, hash: HashAlgorithm, value: &[u8]) -> Result<Object> {
+        match ObjectType::of(format, value)?.0 {
+            ObjectType::Blob => Ok(Object::Blob(Blob::parse(format, value)?)),
+            ObjectType::Tree => Ok(Object::Tree(Tree::parse(format, hash, value)?)),
+            ObjectType::Commit => Ok(Object::Commit(Commit::parse(format, value)?)),
+            ObjectType::Tag => Ok(Object::Tag(Tag::parse(format, value)?)),
+        }
+    }
+
+    pub fn object_type(&self) -> ObjectType {
+        match self {
+            Object::Blob(_) => ObjectType::Blob,
+            Object::Tree(_) => ObjectType::Tree,
+            Object::Commit(_) => ObjectType::Commit,
+            Object::Tag(_) => ObjectType::Tag,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const KEY1: &str = "0123456789abcdef0123456789abcdef01234567";
+    const KEY2: &str = "89abcdef0123456789abcdef0123456789abcdef";
+    const M: ObjectFormat = ObjectFormat::Minigit;
+
+    #[test]
+    fn test_blob() {
+        let blob = Blob { data: b"Hello World!\n".to_vec() };
+        let value = blob.serialize(M);
+        assert!(value.starts_with(b"blob\0"));
+        assert_eq!(Blob::parse(M, &value).unwrap(), blob);
+        assert!(Blob::parse(M, b"blob\0not zlib").is_err());
+        assert!(matches!(Blob::parse(M, b"tree\0"), Err(Error::CorruptObject { key: None, .. })));
+    }
+
+    #[test]
+    fn test_tree() {
+        let tree = Tree { entries: vec![
+            TreeEntry { kind: EntryKind::Tree, key: KEY2.to_string(), name: OsString::from("sub dir") },
+            TreeEntry { kind: EntryKind::Blob, key: KEY1.to_string(), name: OsString::from("a.txt") },
+        ]};
+        let value = tree.serialize(M);
+        assert_eq!(value, format!("tree\0blob {KEY1} a.txt\0tree {KEY2} sub dir\0").into_bytes());
+        let parsed = Tree::parse(M, HashAlgorithm::Sha1, &value).unwrap();
+        assert_eq!(parsed.entries.len(), 2);
+        assert_eq!(parsed.get(&OsString::from("sub dir"), EntryKind::Tree).unwrap().key, KEY2);
+        assert!(Tree::parse(M, HashAlgorithm::Sha1, b"tree\0").unwrap().entries.is_empty());
+        assert!(Tree::parse(M, HashAlgorithm::Sha1, b"tree\0blob short a.txt\0").is_err());
+        assert!(Tree::parse(M, HashAlgorithm::Sha1, format!("tree\0blob {KEY1} a.txt").as_bytes()).is_err());
+        assert!(Tree::parse(M, HashAlgorithm::Sha1, format!("tree\0link {KEY1} a.txt\0").as_bytes()).is_err());
+        // 不是UTF-8的名字在unix上按原样保留，其他系统上视为损坏的对象
+        let mut value = format!("tree\0blob {KEY1} ").into_bytes();
+        value.extend_from_slice(b"\xff.txt\0");
+        let parsed = Tree::parse(M, HashAlgorithm::Sha1, &value);
+        #[cfg(unix)]
+        assert_eq!(parsed.unwrap().serialize(M), value);
+        #[cfg(not(unix))]
+        assert!(matches!(parsed, Err(Error::CorruptObject { .. })));
+    }
+
+    #[test]
+    fn test_commit() {
+        let commit = Commit {
+            parents: vec![KEY1.to_string(), KEY2.to_string()],
+            author: "minigit author".to_string(),
+            datetime: "2024-01-01 00:00:00 UTC".to_string(),
+            message: "first line\nsecond line".to_string(),
+            tree: KEY1.to_string(),
+        };
+        assert_eq!(Commit::parse(M, &commit.serialize(M)).unwrap(), commit);
+        let root = Commit { parents: Vec::new(), tree: Commit::EMPTY_TREE.to_string(), ..commit.clone() };
+        assert_eq!(Commit::parse(M, &root.serialize(M)).unwrap(), root);
+        // 旧版本的根提交
+        let old = format!("commit\0parent \0\nauthor a\ndatetime d\nnote m\ntree {KEY1}");
+        assert!(Commit::parse(M, old.as_bytes()).unwrap().parents.is_empty());
+        assert!(Commit::parse(M, b"commit\0author a\nnote m\ntree x").is_err());
+        assert!(Commit::parse(M, format!("commit\0parent \nauthor a\ndatetime d\nnote m\ntree {KEY1}").as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_git_format() {
+        let git = ObjectFormat::Git;
+        let blob = Blob { data: b"Hello World!\n".to_vec() };
+        assert_eq!(blob.serialize(git), b"blob 13\0Hello World!\n".to_vec());
+        assert_eq!(Blob::parse(git, &blob.serialize(git)).unwrap(), blob);
+        assert!(Blob::parse(git, b"blob 3\0Hello").is_err());
+        assert_eq!(git.load(git.store(b"blob 0\0")).unwrap(), b"blob 0\0".to_vec());
+        // git中文件夹的名字按照后面有'/'排序，所以"a.txt"在"a"之前
+        let tree = Tree { entries: vec![
+            TreeEntry { kind: EntryKind::Tree, key: KEY2.to_string(), name: OsString::from("a") },
+            TreeEntry { kind: EntryKind::Blob, key: KEY1.to_string(), name: OsString::from("a.txt") },
+        ]};
+        let value = tree.serialize(git);
+        assert!(value.starts_with(b"tree 61\x00100644 a.txt\x00\x01\x23"));
+        let parsed = Tree::parse(git, HashAlgorithm::Sha1, &value).unwrap();
+        assert_eq!(parsed.entries.iter().map(|e| e.name.clone()).collect::<Vec<_>>(), vec!["a.txt", "a"]);
+        assert_eq!(parsed.get(&OsString::from("a"), EntryKind::Tree).unwrap().key, KEY2);
+        assert!(Tree::parse(git, HashAlgorithm::Sha256, &value).is_err());
+        let long_key = KEY1.repeat(2)[..64].to_string();
+        let tree = Tree { entries: vec![TreeEntry { kind: EntryKind::Blob, key: long_key.clone(), name: OsString::from("a.txt") }] };
+        assert_eq!(Tree::parse(git, HashAlgorithm::Sha256, &tree.serialize(git)).unwrap(), tree);
+        assert_eq!(Tree::parse(M, HashAlgorithm::Sha256, &tree.serialize(M)).unwrap(), tree);
+        // 符号链接的模式为120000
+        let tree = Tree { entries: vec![TreeEntry { kind: EntryKind::Symlink, key: KEY1.to_string(), name: OsString::from("link") }] };
+        assert!(tree.serialize(git).starts_with(b"tree 32\x00120000 link\x00"));
+        assert_eq!(Tree::parse(git, HashAlgorithm::Sha1, &tree.serialize(git)).unwrap(), tree);
+        assert_eq!(tree.serialize(M), format!("tree\0symlink {KEY1} link\0").into_bytes());
+        assert_eq!(Tree::parse(M, HashAlgorithm::Sha1, &tree.serialize(M)).unwrap(), tree);
+        // 可执行文件的模式为100755
+        let tree = Tree { entries: vec![TreeEntry { kind: EntryKind::Executable, key: KEY1.to_string(), name: OsString::from("run.sh") }] };
+        assert!(tree.serialize(git).starts_with(b"tree 34\x00100755 run.sh\x00"));
+        assert_eq!(Tree::parse(git, HashAlgorithm::Sha1, &tree.serialize(git)).unwrap(), tree);
+        assert_eq!(Tree::parse(M, HashAlgorithm::Sha1, &tree.serialize(M)).unwrap(), tree);
+        let commit = Commit {
+            parents: vec![KEY1.to_string()],
+            author: "minigit author".to_string(),
+            datetime: "2024-01-01 00:00:00 UTC".to_string(),
+            message: "first line\nsecond line".to_string(),
+            tree: KEY2.to_string(),
+        };
+        let value = commit.serialize(git);
+        assert!(String::from_utf8_lossy(&value).contains("author minigit author <> 1704067200 +0000\n"));
+        assert_eq!(Commit::parse(git, &value).unwrap(), commit);
+        let tag = Tag {
+            object: KEY1.to_string(),
+            object_type: ObjectType::Commit,
+            name: "v1.0".to_string(),
+            tagger: "minigit author".to_string(),
+            datetime: "2024-01-01 00:00:00 UTC".to_string(),
+            message: "release\n\nnotes".to_string(),
+        };
+        let value = tag.serialize(git);
+        assert_eq!(value, format!("tag 127\0object {KEY1}\ntype commit\ntag v1.0\ntagger minigit author <> 1704067200 +0000\n\nrelease\n\nnotes\n").into_bytes());
+        assert_eq!(Tag::parse(git, &value).unwrap(), tag);
+    }
+
+    #[test]
+    fn test_tag() {
+        let tag = Tag {
+            object: KEY1.to_string(),
+            object_type: ObjectType::Commit,
+            name: "v1.0".to_string(),
+            tagger: "minigit author".to_string(),
+            datetime: "2024-01-01 00:00:00 UTC".to_string(),
+            message: "first line\nsecond line".to_string(),
+        };
+        let value = tag.serialize(M);
+        assert!(value.starts_with(format!("tag\0object {KEY1}\ntype commit\ntag v1.0\n").as_bytes()));
+        assert_eq!(Tag::parse(M, &value).unwrap(), tag);
+        assert!(Tag::parse(M, format!("tag\0object {KEY1}\ntype commit\ntag v1.0\nnote m").as_bytes()).is_err());
+        assert!(Tag::parse(M, format!("tag\0object {KEY1}\ntype note\ntag v\ntagger a\ndatetime d\nnote m").as_bytes()).is_err());
+        assert_eq!(Object::parse(M, HashAlgorithm::Sha1, &value).unwrap(), Object::Tag(tag));
+        assert_eq!(Object::parse(M, HashAlgorithm::Sha1, b"tree\0").unwrap().object_type(), ObjectType::Tree);
+    }
+}
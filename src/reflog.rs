@@ -0,0 +1,93 @@
+//! minigit 引用日志：记录HEAD和分支每一次变化前后指向的提交
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+/**
+ * 引用日志中的一条记录，每条记录占一行，与git的格式相同
+ * "旧key 新key 身份 <> 时间戳 +0000\t原因"
+ * 引用被创建时旧key全部为0，被删除时新key全部为0
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReflogEntry {
+    pub old_key: String,
+    pub new_key: String,
+    pub identity: String,
+    pub timestamp: i64,
+    pub message: String,
+}
+
+impl ReflogEntry {
+    pub fn parse(line: &str) -> Result<ReflogEntry> {
+        let invalid = || Error::CorruptReflog(format!("invaild entry {line:?}"));
+        let (head, message) = line.split_once('\t').ok_or_else(invalid)?;
+        // 时区总是+0000
+        let (head, _zone) = head.rsplit_once(' ').ok_or_else(invalid)?;
+        let (head, timestamp) = head.rsplit_once(' ').ok_or_else(invalid)?;
+        let mut parts = head.splitn(3, ' ');
+        let (old_key, new_key, who) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(old_key), Some(new_key), Some(who)) => (old_key, new_key, who),
+            _ => return Err(invalid()),
+        };
+        Ok(ReflogEntry {
+            old_key: old_key.to_string(),
+            new_key: new_key.to_string(),
+            identity: who.rsplit_once(" <").map_or(who, |(name, _)| name).to_string(),
+            timestamp: timestamp.parse().map_err(|_| invalid())?,
+            message: message.to_string(),
+        })
+    }
+
+    pub fn serialize(&self) -> String {
+        // 原因只保留一行，避免破坏文件格式
+        let message = self.message.lines().next().unwrap_or("");
+        format!("{} {} {} <> {} +0000\t{}\n", self.old_key, self.new_key, self.identity, self.timestamp, message)
+    }
+}
+
+/**
+ * 'append'在path处的引用日志末尾添加一条记录，文件不存在时创建
+ */
+pub fn append(path: &Path, entry: &ReflogEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(entry.serialize().as_bytes())?;
+    Ok(())
+}
+
+/**
+ * 'read'按写入顺序读取path处引用日志的所有记录，文件不存在时没有记录
+ */
+pub fn read(path: &Path) -> Result<Vec<ReflogEntry>> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(path)?.lines().filter(|line| !line.is_empty()).map(ReflogEntry::parse).collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_reflog_entry() {
+        let entry = ReflogEntry {
+            old_key: "0".repeat(40),
+            new_key: "0123456789abcdef0123456789abcdef01234567".to_string(),
+            identity: "Xiao Ming".to_string(),
+            timestamp: 1700000000,
+            message: "commit (initial): first\nsecond line".to_string(),
+        };
+        let line = entry.serialize();
+        assert_eq!(line, format!("{} {} Xiao Ming <> 1700000000 +0000\tcommit (initial): first\n", entry.old_key, entry.new_key));
+        let parsed = ReflogEntry::parse(line.trim_end()).unwrap();
+        assert_eq!(parsed, ReflogEntry { message: "commit (initial): first".to_string(), ..entry });
+        assert!(matches!(ReflogEntry::parse("broken"), Err(Error::CorruptReflog(_))));
+    }
+}
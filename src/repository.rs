@@ -0,0 +1,2459 @@
+//! minigit 仓库：所有操作都通过 Repository 进行，不依赖当前工作目录
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use chrono::{DateTime, Utc};
+
+use crate::diff;
+use crate::error::{Error, Result};
+use crate::hash::HashAlgorithm;
+use crate::ignore::Ignore;
+use crate::index::{Index, IndexEntry, Stat};
+use crate::lock::LockFile;
+use crate::reflog::{self, ReflogEntry};
+use crate::revision::{Revision, RevisionStep};
+use crate::object::{bytes_to_os_string, parse_datetime, Blob, Commit, EntryKind, Object, ObjectFormat, ObjectType, Tag, Tree, TreeEntry};
+
+/**
+ * 一个minigit仓库，root_path为仓库根目录（工作区），minigit_path为其中的'.minigit'文件夹
+ * identity为切换、创建和删除分支时记录在引用日志中的身份
+ */
+#[derive(Debug, Clone)]
+pub struct Repository {
+    root_path: PathBuf,
+    minigit_path: PathBuf,
+    format: ObjectFormat,
+    hash: HashAlgorithm,
+    identity: String,
+}
+
+/**
+ * 'init'创建仓库时的选项，会记录在'.minigit/config'中
+ * - object_format：对象的保存格式，默认为ObjectFormat::Minigit
+ * - hash_algorithm：计算对象key的哈希算法，默认为HashAlgorithm::Sha1
+ */
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    pub object_format: ObjectFormat,
+    pub hash_algorithm: HashAlgorithm,
+}
+
+/**
+ * 文件相对于比较对象的变化
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileStatus {
+    New,
+    Modified,
+    Deleted,
+}
+
+/**
+ * 'status'的结果，路径均为相对于仓库根目录、以'/'分隔的名字
+ * - branch：当前分支的名字，detached为true时是HEAD指向的提交的key
+ * - staged：暂存区相对于当前分支最新提交的变化
+ * - unstaged：工作区相对于暂存区的变化
+ * - unmerged：合并时产生冲突、还没有用'add'或者'rm'标记为已解决的文件，它们不会出现在staged和unstaged中
+ * - untracked：没有保存到暂存区的文件，整个文件夹都没有保存时以'/'结尾
+ */
+#[derive(Debug, Default)]
+pub struct Status {
+    pub branch: String,
+    pub detached: bool,
+    pub staged: Vec<(String, FileStatus)>,
+    pub unstaged: Vec<(String, FileStatus)>,
+    pub unmerged: Vec<String>,
+    pub untracked: Vec<String>,
+}
+
+/**
+ * 'log'输出的一条提交记录
+ */
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub key: String,
+    pub parents: Vec<String>,
+    pub author: String,
+    pub datetime: String,
+    pub message: String,
+}
+
+/**
+ * 'ls_files'和'ls_tree'列出的一项，path为相对于仓库根目录、以'/'分隔的路径
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListEntry {
+    pub path: String,
+    pub kind: EntryKind,
+    pub key: String,
+}
+
+/**
+ * 'reset'移动当前分支时对暂存区和工作区的处理
+ * - Soft：只移动分支，暂存区和工作区不变
+ * - Mixed：同时用新提交的tree重建暂存区，工作区不变（默认）
+ * - Hard：同时用新提交的tree重建暂存区和工作区
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResetMode {
+    Soft,
+    #[default]
+    Mixed,
+    Hard,
+}
+
+/**
+ * HEAD的指向，保存在'.minigit/HEAD'中
+ * - Branch：指向一个分支，文件内容为"ref: refs/heads/分支名字"（旧版本的仓库中只有分支名字），提交时移动这个分支
+ * - Detached：分离状态，直接指向一个提交，文件内容为提交的key，提交时只移动HEAD
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Head {
+    Branch(String),
+    Detached(String),
+}
+
+impl Head {
+    /**
+     * 'name'返回HEAD指向的分支名字，分离状态时返回"HEAD"
+     */
+    pub fn name(&self)-> &str {
+        match self {
+            Head::Branch(name)=> name,
+            Head::Detached(_)=> "HEAD",
+        }
+    }
+}
+
+/**
+ * HEAD指向分支时文件内容的前缀
+ */
+const HEAD_REF_PREFIX: &str = "ref: refs/heads/";
+
+/**
+ * 'merge'的结果
+ * - UpToDate：要合并的分支已经包含在当前分支中
+ * - FastForward：当前分支直接移动到要合并的分支的最新提交
+ * - Merged：三路合并成功，值为新的合并提交
+ * - Conflict：三路合并有冲突，值为有冲突的文件，解决冲突后的下一次提交会成为合并提交
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeResult {
+    UpToDate,
+    FastForward(String),
+    Merged(String),
+    Conflict(Vec<PathBuf>),
+}
+
+/**
+ * 将path转化为相对于仓库根目录root_path、以'/'分隔的名字，用于输出
+ */
+fn relative_name(root_path: &Path, path: &Path)-> String {
+    let path = path.strip_prefix(root_path).unwrap_or(path);
+    path.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+/**
+ * 工作区中文件对应的tree项类型，metadata需要由'symlink_metadata'得到，不支持的文件类型返回None
+ * 没有可执行权限的系统上所有文件都是普通文件
+ */
+fn entry_kind(metadata: &fs::Metadata)-> Option<EntryKind> {
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        Some(EntryKind::Symlink)
+    }
+    else if file_type.is_dir() {
+        Some(EntryKind::Tree)
+    }
+    else if file_type.is_file() && is_executable(metadata) {
+        Some(EntryKind::Executable)
+    }
+    else if file_type.is_file() {
+        Some(EntryKind::Blob)
+    }
+    else {
+        None
+    }
+}
+
+/**
+ * 文件的所有者、组或者其他用户中任意一个有执行权限时视为可执行文件
+ */
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata)-> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata)-> bool {
+    false
+}
+
+/**
+ * 按照kind设置文件path的执行权限，有读权限的用户同时得到执行权限，权限没有变化时不修改文件
+ */
+#[cfg(unix)]
+fn set_executable(path: &Path, kind: EntryKind)-> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    let mode = permissions.mode();
+    let new_mode = match kind {
+        EntryKind::Executable=> mode | ((mode & 0o444) >> 2),
+        _=> mode & !0o111,
+    };
+    if new_mode != mode {
+        permissions.set_mode(new_mode);
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path, _kind: EntryKind)-> Result<()> {
+    Ok(())
+}
+
+/**
+ * 删除文件夹path下被暂存区跟踪的文件和符号链接，没有被跟踪的文件（包括被忽略的文件）保留下来，删除后为空的文件夹也会被删除
+ */
+fn clear_worktree(path: &Path, index: &Index)-> Result<()> {
+    for entry in index.children(path) {
+        // 已经被删除的文件不用处理，类型改变的文件留给调用者检查
+        let metadata = match fs::symlink_metadata(&entry.path) {
+            Err(_)=> continue,
+            Ok(metadata)=> metadata,
+        };
+        if entry.kind == EntryKind::Tree && metadata.is_dir() {
+            clear_worktree(&entry.path, index)?;
+            if entry.path.read_dir()?.next().is_none() {
+                fs::remove_dir(&entry.path)?;
+            }
+        }
+        else if entry.kind != EntryKind::Tree && !metadata.is_dir() {
+            fs::remove_file(&entry.path)?;
+        }
+    }
+    Ok(())
+}
+
+/**
+ * 检查分支或者标签的名字，kind为"branch"或者"tag"，只用于错误信息
+ * 名字直接作为refs下的文件名，不能为空，不能包含'/'、'\'、".."和控制字符，不能以'.'开头或者以".lock"结尾，否则可能写到refs之外；
+ * 也不能是HEAD、'@'，或者包含版本表达式中有特殊含义的'~'、'^'、':'、"@{"，否则无法用版本表达式引用
+ */
+fn check_ref_name(kind: &str, name: &str)-> Result<()> {
+    let invaild = name.is_empty() || name == "HEAD" || name == "@" || name.starts_with('.') || name.ends_with(".lock")
+        || name.contains("..") || name.contains("@{")
+        || name.chars().any(|c| c.is_control() || matches!(c, '/' | '\\' | '~' | '^' | ':'));
+    if invaild {
+        return Err(Error::Usage(format!("invaild {kind} name {name}")));
+    }
+    Ok(())
+}
+
+/**
+ * 按照'diff_worktree'的格式把一个文件的修改写入out，old和new为修改前后文件的类型和内容，None表示文件不存在
+ */
+fn write_file_diff(out: &mut Vec<u8>, name: &str, old: Option<(EntryKind, Vec<u8>)>, new: Option<(EntryKind, Vec<u8>)>, context: usize) {
+    out.extend_from_slice(format!("diff --minigit a/{name} b/{name}\n").as_bytes());
+    match (&old, &new) {
+        (Some((old_kind, old_data)), Some((new_kind, new_data)))=> {
+            if old_kind != new_kind {
+                out.extend_from_slice(format!("old mode {}\nnew mode {}\n", old_kind.git_mode(), new_kind.git_mode()).as_bytes());
+            }
+            if old_data != new_data {
+                out.extend_from_slice(format!("--- a/{name}\n+++ b/{name}\n").as_bytes());
+            }
+        },
+        (None, _)=> out.extend_from_slice(format!("new file\n--- /dev/null\n+++ b/{name}\n").as_bytes()),
+        (_, None)=> out.extend_from_slice(format!("deleted file\n--- a/{name}\n+++ /dev/null\n").as_bytes()),
+    }
+    let (old_data, new_data) = (old.map(|(_, data)| data).unwrap_or_default(), new.map(|(_, data)| data).unwrap_or_default());
+    out.append(&mut diff::unified_diff(&old_data, &new_data, context));
+}
+
+/**
+ * 暂存区中的全部文件（不包括文件夹）以及它们的类型和key
+ */
+fn index_files(index: &Index)-> BTreeMap<PathBuf, (EntryKind, String)> {
+    index.entries().iter()
+         .filter(|entry| entry.kind != EntryKind::Tree)
+         .map(|entry| (entry.path.clone(), (entry.kind, entry.key.clone())))
+         .collect()
+}
+
+/**
+ * config文件的内容，设置写在'[minigit]'小节中，与git的config格式相同，git的工具读取'.minigit'时不会报错
+ * git格式使用sha256时还要写入git的'extensions.objectFormat'，git需要它才能按sha256读取对象，
+ * 使用extensions时'core.repositoryformatversion'必须为1
+ */
+fn config_content(options: &InitOptions)-> String {
+    let mut content = format!("[minigit]\n\tformat = {}\n\thash = {}\n", options.object_format.as_str(), options.hash_algorithm.as_str());
+    if options.object_format == ObjectFormat::Git && options.hash_algorithm == HashAlgorithm::Sha256 {
+        content.push_str("[core]\n\trepositoryformatversion = 1\n[extensions]\n\tobjectFormat = sha256\n");
+    }
+    content
+}
+
+
+impl Repository {
+    /**
+     * 'init'在path下创建minigit仓库，如果该仓库已经存在则保留其中所有的对象、引用和引用日志，只补上缺少的文件夹和文件
+     * # 示例
+     * ```
+        let path = std::env::temp_dir().join("minigit-doc-init");
+        let repository = minigit::Repository::init(&path).unwrap();
+        assert!(path.join(".minigit").is_dir());
+        assert_eq!(repository.current_branch().unwrap(), "master");
+     * ```
+     */
+    pub fn init(path: impl AsRef<Path>)-> Result<Repository> {
+        Repository::init_with_options(path, &InitOptions::default())
+    }
+
+    /**
+     * 'init_with_options'在path下创建minigit仓库，并使用options中的设置
+     * 仓库已经存在时与'init'相同不会删除任何内容，options与仓库已有的对象格式或哈希算法不同时返回Error::Usage
+     * # 示例
+     * ```
+        let path = std::env::temp_dir().join("minigit-doc-init-git");
+        let options = minigit::InitOptions { object_format: minigit::ObjectFormat::Git, ..Default::default() };
+        let repository = minigit::Repository::init_with_options(&path, &options).unwrap();
+        assert_eq!(repository.object_format(), minigit::ObjectFormat::Git);
+     * ```
+     */
+    pub fn init_with_options(path: impl AsRef<Path>, options: &InitOptions)-> Result<Repository> {
+        let root_path = path.as_ref().to_path_buf();
+        let path = root_path.join(".minigit");
+        if path.is_dir() {
+            // 已有的对象不能换一种格式读取，不允许修改
+            let repository = Repository::open(&root_path)?;
+            if repository.format != options.object_format || repository.hash != options.hash_algorithm {
+                return Err(Error::Usage(format!("repository {} already uses {} format and {} hash, they can't be changed",
+                                                root_path.display(), repository.format.as_str(), repository.hash.as_str())));
+            }
+        }
+        fs::create_dir_all(path.join("refs/heads"))?;
+        fs::create_dir_all(path.join("refs/tags"))?;
+        fs::create_dir_all(path.join("objects"))?;
+        // 重新初始化时只创建缺少的文件，已有的HEAD和index保持不变
+        if !path.join("index").exists() {
+            File::create(path.join("index"))?;
+        }
+        if !path.join("HEAD").exists() {
+            let mut head = File::create(path.join("HEAD"))?;
+            head.write_all(format!("{HEAD_REF_PREFIX}master").as_bytes())?;
+        }
+        // config的设置与已有仓库相同，重写时会把旧版本没有小节的config转为新的格式
+        fs::write(path.join("config"), config_content(options))?;
+        Repository::open(root_path)
+    }
+
+    /**
+     * 'open'打开根目录为path的仓库，path下必须有'.minigit'文件夹
+     */
+    pub fn open(path: impl AsRef<Path>)-> Result<Repository> {
+        let root_path = fs::canonicalize(path.as_ref())?;
+        let minigit_path = root_path.join(".minigit");
+        if !minigit_path.is_dir() {
+            return Err(Error::NotARepository(root_path));
+        }
+        let mut format = ObjectFormat::Minigit;
+        let mut hash = HashAlgorithm::Sha1;
+        // 旧版本的仓库没有config文件，使用Minigit格式和SHA-1
+        let config_path = minigit_path.join("config");
+        if config_path.is_file() {
+            // 设置在'[minigit]'小节中，旧版本的config没有小节，第一个小节之前的设置也属于minigit
+            let mut in_minigit = true;
+            for line in fs::read_to_string(&config_path)?.lines() {
+                let line = line.trim();
+                if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                    in_minigit = section.trim() == "minigit";
+                    continue;
+                }
+                if !in_minigit {
+                    continue;
+                }
+                match line.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+                    Some(("format", value))=> {
+                        format = ObjectFormat::from_name(value).ok_or_else(|| Error::CorruptConfig(format!("unknown object format {value}")))?;
+                    },
+                    Some(("hash", value))=> {
+                        hash = HashAlgorithm::from_name(value).ok_or_else(|| Error::CorruptConfig(format!("unknown hash algorithm {value}")))?;
+                    },
+                    _=> {},
+                }
+            }
+        }
+        Ok(Repository { root_path, minigit_path, format, hash, identity: "unknown".to_string() })
+    }
+
+    /**
+     * 'discover'从path开始向上寻找'.minigit'文件夹（也就是minigit库配置文件存放的地方），并打开对应的仓库
+     */
+    pub fn discover(path: impl AsRef<Path>)-> Result<Repository> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(Error::InvalidPath(path.to_path_buf()));
+        }
+        let mut start_path = fs::canonicalize(path)?;
+        if start_path.is_file() {
+            start_path.pop();
+        }
+        let target = OsStr::new(".minigit");
+        loop{
+            if start_path.join(target).is_dir() {
+                return Repository::open(&start_path);
+            }
+            if !start_path.pop() {
+                break;
+            }
+        }
+        Err(Error::NotARepository(path.to_path_buf()))
+    }
+
+    pub fn root_path(&self)-> &Path {
+        &self.root_path
+    }
+
+    pub fn minigit_path(&self)-> &Path {
+        &self.minigit_path
+    }
+
+    pub fn object_format(&self)-> ObjectFormat {
+        self.format
+    }
+
+    pub fn hash_algorithm(&self)-> HashAlgorithm {
+        self.hash
+    }
+
+    /**
+     * 'set_identity'设置引用日志中记录的身份，提交和合并时使用作者作为身份
+     */
+    pub fn set_identity(&mut self, identity: &str) {
+        self.identity = identity.to_string();
+    }
+
+    /**
+     * 生成path代表的文件对应的blob对象内容，但不写入objects
+     */
+    fn blob_value(&self, path: &Path)-> Result<Vec<u8>> {
+        Ok(Blob::serialize_from(self.format, File::open(path)?)?)
+    }
+
+    /**
+     * 生成工作区中path对应的blob对象内容，符号链接的内容为它指向的路径
+     */
+    fn file_value(&self, path: &Path, kind: EntryKind)-> Result<Vec<u8>> {
+        match kind {
+            EntryKind::Symlink=> Ok(Blob { data: fs::read_link(path)?.into_os_string().into_encoded_bytes() }.serialize(self.format)),
+            _=> self.blob_value(path),
+        }
+    }
+
+    /**
+     * 相对路径视为相对于仓库根目录，并检查路径在仓库中
+     */
+    fn resolve(&self, path: &Path)-> Result<PathBuf> {
+        // 按字面去掉'.'和'..'，保证index中记录的路径与read_dir得到的路径一致
+        let mut normal = PathBuf::new();
+        for component in self.root_path.join(path).components() {
+            match component {
+                Component::CurDir=> {},
+                Component::ParentDir=> { normal.pop(); },
+                other=> normal.push(other),
+            }
+        }
+        let path = normal;
+        if !path.starts_with(&self.root_path) {
+            return Err(Error::OutsideRepository(path));
+        }
+        Ok(path)
+    }
+
+
+    /**
+     * 从路径path开始通过index里面的记录而不是实际文件系统来更新path所有祖先文件夹的key
+     */
+    fn updata_index(&self, index: &mut Index, path: &Path)-> Result<()> {
+        let path = match path.parent() {
+            None => return Err(Error::OutsideRepository(path.to_path_buf())),
+            Some(p)=> p.to_path_buf(),
+        };
+        if !path.starts_with(&self.root_path) {
+            return Ok(());
+        }
+        // 根据index里面的记录而不是实际文件系统来更新path_ancestor
+        self.index_tree(index, &path)?;
+        self.updata_index(index, &path)
+    }
+
+    /**
+     * 用暂存区中文件夹path下的记录生成并保存它的tree对象，同时更新path的记录
+     */
+    fn index_tree(&self, index: &mut Index, path: &Path)-> Result<String> {
+        let mut tree = Tree::default();
+        for entry in index.children(path) {
+            let name = entry.path.file_name().unwrap_or_default().to_os_string();
+            tree.entries.push(TreeEntry { kind: entry.kind, key: entry.key.clone(), name });
+        }
+        let key = self.save_value(&tree.serialize(self.format))?;
+        index.insert(IndexEntry { path: path.to_path_buf(), kind: EntryKind::Tree, key: key.clone(), stat: Stat::default() });
+        Ok(key)
+    }
+
+    /**
+     * 读取仓库的忽略规则
+     */
+    fn load_ignore(&self)-> Result<Ignore> {
+        Ignore::load(&self.root_path, &self.minigit_path.join("info").join("exclude"))
+    }
+
+    /**
+     * 被忽略的文件只有在已经被暂存区跟踪时才会保存
+     */
+    fn is_skipped(&self, index: &Index, ignore: &Ignore, path: &Path, is_dir: bool)-> Result<bool> {
+        Ok(index.get(path).is_none() && ignore.is_ignored(path, is_dir)?)
+    }
+
+    fn index_path(&self)-> PathBuf {
+        self.minigit_path.join("index")
+    }
+
+    /**
+     * 读取暂存区
+     */
+    fn read_index(&self)-> Result<Index> {
+        Index::load(&self.index_path(), self.hash)
+    }
+
+    /**
+     * 修改暂存区前先获取index的锁，在读取index之前获取，避免覆盖其他进程的修改
+     */
+    fn lock_index(&self)-> Result<LockFile> {
+        LockFile::acquire(&self.index_path())
+    }
+
+    fn write_index(&self, lock: LockFile, index: &Index)-> Result<()> {
+        index.write(lock, self.hash)
+    }
+
+    /**
+     * 通过锁文件写入引用（分支、HEAD等），写完后再替换原文件
+     */
+    fn write_ref(&self, path: &Path, value: &str)-> Result<()> {
+        let mut lock = LockFile::acquire(path)?;
+        lock.write_all(value.as_bytes())?;
+        lock.commit()
+    }
+
+    fn reflog_path(&self, branch_name: Option<&str>)-> PathBuf {
+        let logs_path = self.minigit_path.join("logs");
+        match branch_name {
+            None=> logs_path.join("HEAD"),
+            Some(name)=> logs_path.join("refs").join("heads").join(name),
+        }
+    }
+
+    /**
+     * 在分支branch_name（None表示HEAD）的引用日志中记录一次变化，old_key或new_key为None时记为全0
+     */
+    fn append_reflog(&self, branch_name: Option<&str>, old_key: Option<&str>, new_key: Option<&str>, identity: &str, message: &str)-> Result<()> {
+        let zero = "0".repeat(self.hash.hex_len());
+        let entry = ReflogEntry {
+            old_key: old_key.unwrap_or(&zero).to_string(),
+            new_key: new_key.unwrap_or(&zero).to_string(),
+            identity: identity.to_string(),
+            timestamp: Utc::now().timestamp(),
+            message: message.to_string(),
+        };
+        reflog::append(&self.reflog_path(branch_name), &entry)
+    }
+
+    /**
+     * 分支branch_name的最新提交，分支还没有提交时为None
+     */
+    fn branch_commit(&self, branch_name: &str)-> Result<Option<String>> {
+        let branch_path = self.branch_path(branch_name);
+        if branch_path.is_file() {
+            Ok(Some(fs::read_to_string(branch_path)?))
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+
+    fn save_value(&self, value: &[u8])-> Result<String> {
+        if !self.minigit_path.is_dir() {
+            return Err(Error::NotARepository(self.root_path.clone()));
+        }
+        let key: &str = &self.hash.hash(value);
+        let save_path = self.minigit_path.join("objects").join(&key[0..2]);
+        if !save_path.is_dir(){
+            fs::create_dir(&save_path)?;
+        }
+        let save_path = save_path.join(&key[2..]);
+        if !save_path.is_file(){
+            let mut save_file = File::create(save_path)?;
+            save_file.write_all(&self.format.store(value))?;
+        }
+        Ok(String::from(key))
+    }
+
+    /**
+     * 保存文件（或者符号链接）并记录到暂存区，文件状态与暂存区中的记录相同时直接使用记录的key
+     */
+    fn save_blob(&self, index: &mut Index, path: &Path, kind: EntryKind)-> Result<String> {
+        let metadata = fs::symlink_metadata(path)?;
+        if let Some(key) = index.get(path).filter(|entry| entry.kind == kind).and(index.cached_key(path, &metadata)) {
+            return Ok(key.to_string());
+        }
+        let value = self.file_value(path, kind)?;
+        let key = self.save_value(&value)?;
+        // 原来的文件夹变成了文件时，文件夹中的记录也要删除
+        if index.get(path).is_some_and(|entry| entry.kind == EntryKind::Tree) {
+            self.forget_index(index, path);
+        }
+        index.insert(IndexEntry { path: path.to_path_buf(), kind, key: key.clone(), stat: Stat::from_metadata(&metadata) });
+        Ok(key)
+    }
+
+    fn save_tree(&self, index: &mut Index, ignore: &Ignore, path: &Path)-> Result<String> {
+        let mut tree = Tree::default();
+        for entry in path.read_dir()? {
+            let entry = entry?;
+            let child_path = entry.path();
+            let metadata = entry.metadata()?;
+            if self.is_skipped(index, ignore, &child_path, metadata.is_dir())? {
+                continue;
+            }
+            let kind = entry_kind(&metadata).ok_or_else(|| Error::UnsupportedFile(child_path.clone()))?;
+            let key = match kind {
+                EntryKind::Tree=> self.save_tree(index, ignore, &child_path)?,
+                _=> self.save_blob(index, &child_path, kind)?,
+            };
+            tree.entries.push(TreeEntry { kind, key, name: entry.file_name() });
+        }
+        self.forget_missing(index, path);
+        let dir_key = self.save_value(&tree.serialize(self.format))?;
+        index.insert(IndexEntry { path: path.to_path_buf(), kind: EntryKind::Tree, key: dir_key.clone(), stat: Stat::default() });
+        Ok(dir_key)
+    }
+
+    /**
+     * 从暂存区中删除path以及它下面的全部记录
+     */
+    fn forget_index(&self, index: &mut Index, path: &Path) {
+        let children = index.children(path).iter().map(|entry| entry.path.clone()).collect::<Vec<PathBuf>>();
+        for child in children {
+            self.forget_index(index, &child);
+        }
+        index.remove(path);
+    }
+
+    /**
+     * 文件夹dir中已经被删除的文件也要从暂存区中删除，指向不存在的路径的符号链接不算被删除
+     */
+    fn forget_missing(&self, index: &mut Index, dir: &Path) {
+        let missing = index.children(dir).iter()
+                           .filter(|entry| fs::symlink_metadata(&entry.path).is_err())
+                           .map(|entry| entry.path.clone())
+                           .collect::<Vec<PathBuf>>();
+        for path in missing {
+            self.forget_index(index, &path);
+        }
+    }
+
+    fn save_object(&self, index: &mut Index, ignore: &Ignore, path: &Path)-> Result<()> {
+        let minigit_path = &self.minigit_path;
+        if path == minigit_path {
+            return Ok(());
+        }
+        if path.starts_with(minigit_path) {
+            return Err(Error::InvalidPath(path.to_path_buf()));
+        }
+        let kind = match fs::symlink_metadata(path) {
+            Err(_)=> return Err(Error::InvalidPath(path.to_path_buf())),
+            Ok(metadata)=> entry_kind(&metadata).ok_or_else(|| Error::UnsupportedFile(path.to_path_buf()))?,
+        };
+        if self.is_skipped(index, ignore, path, kind == EntryKind::Tree)? {
+            return Err(Error::IgnoredPath(path.to_path_buf()));
+        }
+        if kind != EntryKind::Tree {
+            self.save_blob(index, path, kind)?;
+        }
+        else if path == self.root_path {
+            let minigit = OsStr::new(".minigit");
+            for entry in path.read_dir()? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if entry.file_name() == minigit || self.is_skipped(index, ignore, &entry.path(), metadata.is_dir())? {
+                    continue;
+                }
+                match entry_kind(&metadata) {
+                    None=> return Err(Error::UnsupportedFile(entry.path())),
+                    Some(EntryKind::Tree)=> { self.save_tree(index, ignore, &entry.path())?; },
+                    Some(kind)=> { self.save_blob(index, &entry.path(), kind)?; },
+                }
+            }
+            self.forget_missing(index, path);
+            return self.updata_index(index, minigit_path);
+        }
+        else {
+            self.save_tree(index, ignore, path)?;
+        }
+        self.updata_index(index, path)
+    }
+
+    /**
+     * 'add'负责将一系列文件或者文件夹保存到索引，如果已经保存则检查是否有改变，如果有改变则保存改变后的新文件到索引
+     * 相对路径视为相对于仓库根目录，文件状态没有变化的文件不会重新读取
+     */
+    pub fn add<P: AsRef<Path>>(&self, paths: &[P])-> Result<()> {
+        let lock = self.lock_index()?;
+        let mut index = self.read_index()?;
+        let ignore = self.load_ignore()?;
+        let mut resolved = Vec::new();
+        for path in paths {
+            let path = self.resolve(path.as_ref())?;
+            self.save_object(&mut index, &ignore, &path)?;
+            resolved.push(path);
+        }
+        self.write_index(lock, &index)?;
+        self.mark_resolved(&resolved)
+    }
+
+
+    /**
+     * 删除文件或者符号链接（不会删除链接指向的文件）
+     */
+    fn remove_blob(&self, index: &mut Index, path: &Path)-> Result<()> {
+        fs::remove_file(path)?;
+        index.remove(path);
+        Ok(())
+    }
+
+    fn remove_tree(&self, index: &mut Index, path: &Path)-> Result<()> {
+        for entry in path.read_dir()? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                self.remove_tree(index, &entry.path())?;
+            }
+            else {
+                self.remove_blob(index, &entry.path())?;
+            }
+        }
+        fs::remove_dir(path)?;
+        self.forget_index(index, path);
+        Ok(())
+    }
+
+    fn remove_object(&self, index: &mut Index, path: &Path)-> Result<()> {
+        let minigit_path = &self.minigit_path;
+        if path == minigit_path {
+            return Ok(());
+        }
+        if path.starts_with(minigit_path) {
+            return Err(Error::InvalidPath(path.to_path_buf()));
+        }
+        let kind = match fs::symlink_metadata(path) {
+            Err(_)=> return Err(Error::InvalidPath(path.to_path_buf())),
+            Ok(metadata)=> entry_kind(&metadata).ok_or_else(|| Error::UnsupportedFile(path.to_path_buf()))?,
+        };
+        if kind != EntryKind::Tree {
+            self.remove_blob(index, path)?;
+        }
+        else if path == self.root_path {
+            let minigit = OsStr::new(".minigit");
+            for entry in path.read_dir()? {
+                let entry = entry?;
+                if entry.file_name() == minigit {
+                    continue;
+                }
+                match entry_kind(&entry.metadata()?) {
+                    None=> return Err(Error::UnsupportedFile(entry.path())),
+                    Some(EntryKind::Tree)=> self.remove_tree(index, &entry.path())?,
+                    Some(_)=> self.remove_blob(index, &entry.path())?,
+                }
+            }
+        }
+        else {
+            self.remove_tree(index, path)?;
+        }
+        self.updata_index(index, path)
+    }
+
+    /**
+     * 'rm'将工作区的文件删除，并且将此次删除保存到暂存区，相对路径视为相对于仓库根目录
+     */
+    pub fn rm<P: AsRef<Path>>(&self, paths: &[P])-> Result<()> {
+        let lock = self.lock_index()?;
+        let mut index = self.read_index()?;
+        let mut resolved = Vec::new();
+        for path in paths {
+            let path = self.resolve(path.as_ref())?;
+            self.remove_object(&mut index, &path)?;
+            resolved.push(path);
+        }
+        self.write_index(lock, &index)?;
+        self.mark_resolved(&resolved)
+    }
+
+    /**
+     * 'rm_cached'只从暂存区中删除paths下的记录，工作区中的文件保留下来成为未跟踪的文件
+     */
+    pub fn rm_cached<P: AsRef<Path>>(&self, paths: &[P])-> Result<()> {
+        let lock = self.lock_index()?;
+        let mut index = self.read_index()?;
+        let mut resolved = Vec::new();
+        for path in paths {
+            let path = self.resolve(path.as_ref())?;
+            if index.get(&path).is_none() {
+                return Err(Error::InvalidPath(path));
+            }
+            self.forget_index(&mut index, &path);
+            self.updata_index(&mut index, &path)?;
+            resolved.push(path);
+        }
+        self.write_index(lock, &index)?;
+        self.mark_resolved(&resolved)
+    }
+
+
+    fn create_tree_from_index(&self)-> Result<String> {
+        // 仓库根目录对应的记录就是整个仓库的tree
+        match self.read_index()?.get(&self.root_path) {
+            // git没有空tree的特殊写法，需要真正保存一个空的tree对象
+            None if self.format == ObjectFormat::Git=> self.save_value(&Tree::default().serialize(self.format)),
+            None=> Ok(Commit::EMPTY_TREE.to_string()),
+            Some(entry)=> Ok(entry.key.clone()),
+        }
+    }
+
+    /**
+     * 'commit'提交暂存区，返回新提交的key
+     */
+    pub fn commit(&self, author: &str, message: &str)-> Result<String> {
+        self.commit_with_parents(author, message, &[])
+    }
+
+    /**
+     * 'commit_with_parents'提交暂存区，除了当前分支的最新提交之外，还会把merge_parents中的提交记录为父提交（用于合并提交）
+     */
+    fn commit_with_parents(&self, author: &str, message: &str, merge_parents: &[String])-> Result<String> {
+        let head = self.head()?;
+        let mut ref_lock = LockFile::acquire(&self.head_ref_path(&head))?;
+        let tree_key = self.create_tree_from_index()?;
+        let old_key = self.head_key(&head)?;
+        let mut parents: Vec<String> = old_key.iter().cloned().collect();
+        parents.extend(merge_parents.iter().cloned());
+        let merge_head = self.minigit_path.join("MERGE_HEAD");
+        if merge_head.is_file() {
+            // 合并的冲突全部解决之后才能提交
+            let conflicts = self.read_conflicts()?;
+            if !conflicts.is_empty() {
+                return Err(Error::MergeConflict(conflicts.into_iter().collect()));
+            }
+            let key = fs::read_to_string(&merge_head)?;
+            if !parents.contains(&key) {
+                parents.push(key);
+            }
+        }
+        let now: DateTime<Utc> = Utc::now();
+        let commit_value = Commit {
+            parents,
+            author: author.to_string(),
+            datetime: now.to_string(),
+            message: message.to_string(),
+            tree: tree_key,
+        };
+        let key = self.save_value(&commit_value.serialize(self.format))?;
+        ref_lock.write_all(key.as_bytes())?;
+        ref_lock.commit()?;
+        let reason = match commit_value.parents.len() {
+            0=> "commit (initial)",
+            1=> "commit",
+            _=> "commit (merge)",
+        };
+        let reason = format!("{reason}: {}", message.lines().next().unwrap_or(""));
+        self.log_head_update(&head, old_key.as_deref(), Some(&key), author, &reason)?;
+        if merge_head.is_file() {
+            fs::remove_file(merge_head)?;
+        }
+        Ok(key)
+    }
+
+
+    fn branch_path(&self, name: &str)-> PathBuf {
+        self.minigit_path.join("refs").join("heads").join(name)
+    }
+
+    fn head_path(&self)-> PathBuf {
+        self.minigit_path.join("HEAD")
+    }
+
+    /**
+     * 'head'读取HEAD的指向
+     */
+    pub fn head(&self)-> Result<Head> {
+        let value = fs::read_to_string(self.head_path())?;
+        let value = value.trim_end();
+        if let Some(name) = value.strip_prefix(HEAD_REF_PREFIX) {
+            return Ok(Head::Branch(name.to_string()));
+        }
+        // 旧版本的仓库中HEAD只保存分支名字
+        if self.hash.is_valid_key(value) && !self.branch_path(value).is_file() {
+            Ok(Head::Detached(value.to_string()))
+        }
+        else {
+            Ok(Head::Branch(value.to_string()))
+        }
+    }
+
+    /**
+     * 通过锁文件修改HEAD的指向
+     */
+    fn write_head(&self, head: &Head)-> Result<()> {
+        let value = match head {
+            Head::Branch(name)=> format!("{HEAD_REF_PREFIX}{name}"),
+            Head::Detached(key)=> key.clone(),
+        };
+        self.write_ref(&self.head_path(), &value)
+    }
+
+    /**
+     * 提交、合并和reset时实际移动的引用文件：指向分支时为分支，分离状态时为HEAD本身
+     */
+    fn head_ref_path(&self, head: &Head)-> PathBuf {
+        match head {
+            Head::Branch(name)=> self.branch_path(name),
+            Head::Detached(_)=> self.head_path(),
+        }
+    }
+
+    /**
+     * head指向的提交，指向的分支还没有提交时为None
+     */
+    fn head_key(&self, head: &Head)-> Result<Option<String>> {
+        match head {
+            Head::Branch(name)=> self.branch_commit(name),
+            Head::Detached(key)=> Ok(Some(key.clone())),
+        }
+    }
+
+    /**
+     * 在HEAD以及它指向的分支的引用日志中记录一次移动
+     */
+    fn log_head_update(&self, head: &Head, old_key: Option<&str>, new_key: Option<&str>, identity: &str, message: &str)-> Result<()> {
+        if let Head::Branch(name) = head {
+            self.append_reflog(Some(name), old_key, new_key, identity, message)?;
+        }
+        self.append_reflog(None, old_key, new_key, identity, message)
+    }
+
+    /**
+     * 'current_branch'返回HEAD指向的分支名字，HEAD处于分离状态时返回Error::DetachedHead
+     */
+    pub fn current_branch(&self)-> Result<String> {
+        match self.head()? {
+            Head::Branch(name)=> Ok(name),
+            Head::Detached(key)=> Err(Error::DetachedHead(key)),
+        }
+    }
+
+    /**
+     * 'branches'返回所有已经有提交的分支名字，按名字排序
+     */
+    pub fn branches(&self)-> Result<Vec<String>> {
+        let branchs_path = self.minigit_path.join("refs").join("heads");
+        let mut re = Vec::new();
+        for entry in branchs_path.read_dir()? {
+            let name = entry?.file_name().into_string().map_err(|name| Error::InvalidPath(branchs_path.join(name)))?;
+            // 正在被修改的分支留下的锁文件不是分支
+            if !name.ends_with(".lock") {
+                re.push(name);
+            }
+        }
+        re.sort();
+        Ok(re)
+    }
+
+    /**
+     * 'create_branch'在HEAD指向的提交上创建新分支
+     */
+    pub fn create_branch(&self, name: &str)-> Result<()> {
+        self.write_branch(name, None)
+    }
+
+    /**
+     * 'create_branch_at'在版本表达式start指向的提交上创建新分支
+     */
+    pub fn create_branch_at(&self, name: &str, start: &str)-> Result<()> {
+        self.write_branch(name, Some(start))
+    }
+
+    fn write_branch(&self, name: &str, start: Option<&str>)-> Result<()> {
+        check_ref_name("branch", name)?;
+        let branch_path = self.branch_path(name);
+        let mut lock = LockFile::acquire(&branch_path)?;
+        if branch_path.is_file() {
+            return Err(Error::BranchExists(name.to_string()));
+        }
+        let (last_commit_key, from) = match start {
+            Some(start)=> (self.resolve_commit(start)?, start.to_string()),
+            None=> (self.head_commit()?, self.head()?.name().to_string()),
+        };
+        lock.write_all(last_commit_key.as_bytes())?;
+        lock.commit()?;
+        self.append_reflog(Some(name), None, Some(&last_commit_key), &self.identity, &format!("branch: Created from {from}"))
+    }
+
+    /**
+     * 'delete_branch'删除分支，不能删除当前分支
+     */
+    pub fn delete_branch(&self, name: &str)-> Result<()> {
+        check_ref_name("branch", name)?;
+        let branch_path = self.branch_path(name);
+        if self.head()? == Head::Branch(name.to_string()) {
+            return Err(Error::DeleteCurrentBranch(name.to_string()));
+        }
+        let _lock = LockFile::acquire(&branch_path)?;
+        if let Some(key) = self.branch_commit(name)? {
+            fs::remove_file(&branch_path)?;
+            // 保留被删除分支的引用日志，之后仍然可以找回它的提交
+            self.append_reflog(Some(name), Some(&key), None, &self.identity, "branch: deleted")
+        }
+        else {
+            Err(Error::BranchNotFound(name.to_string()))
+        }
+    }
+
+    fn tags_path(&self)-> PathBuf {
+        self.minigit_path.join("refs").join("tags")
+    }
+
+    /**
+     * 标签name指向的key（轻量标签为提交，附注标签为标签对象），标签不存在时为None
+     */
+    fn tag_key(&self, name: &str)-> Result<Option<String>> {
+        let tag_path = self.tags_path().join(name);
+        if tag_path.is_file() {
+            Ok(Some(fs::read_to_string(tag_path)?))
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+    /**
+     * 'tags'返回所有标签的名字，按名字排序
+     */
+    pub fn tags(&self)-> Result<Vec<String>> {
+        let tags_path = self.tags_path();
+        let mut re = Vec::new();
+        // 旧版本的仓库没有'refs/tags'文件夹
+        if !tags_path.is_dir() {
+            return Ok(re);
+        }
+        for entry in tags_path.read_dir()? {
+            let name = entry?.file_name().into_string().map_err(|name| Error::InvalidPath(tags_path.join(name)))?;
+            if !name.ends_with(".lock") {
+                re.push(name);
+            }
+        }
+        re.sort();
+        Ok(re)
+    }
+
+    /**
+     * 'create_tag'在提交target（None表示HEAD指向的提交）上创建轻量标签，标签直接指向提交，返回提交的key
+     */
+    pub fn create_tag(&self, name: &str, target: Option<&str>)-> Result<String> {
+        self.write_tag(name, target, None)
+    }
+
+    /**
+     * 'create_annotated_tag'在提交target上创建附注标签，标签指向记录了创建者、时间和信息的标签对象，返回标签对象的key
+     */
+    pub fn create_annotated_tag(&self, name: &str, target: Option<&str>, tagger: &str, message: &str)-> Result<String> {
+        self.write_tag(name, target, Some((tagger, message)))
+    }
+
+    fn write_tag(&self, name: &str, target: Option<&str>, annotation: Option<(&str, &str)>)-> Result<String> {
+        check_ref_name("tag", name)?;
+        fs::create_dir_all(self.tags_path())?;
+        let tag_path = self.tags_path().join(name);
+        let mut lock = LockFile::acquire(&tag_path)?;
+        if tag_path.is_file() {
+            return Err(Error::TagExists(name.to_string()));
+        }
+        let commit_key = match target {
+            Some(target)=> self.resolve_commit(target)?,
+            None=> self.head_commit()?,
+        };
+        let key = match annotation {
+            None=> commit_key,
+            Some((tagger, message))=> {
+                let tag = Tag {
+                    object: commit_key,
+                    object_type: ObjectType::Commit,
+                    name: name.to_string(),
+                    tagger: tagger.to_string(),
+                    datetime: Utc::now().to_string(),
+                    message: message.to_string(),
+                };
+                self.save_value(&tag.serialize(self.format))?
+            },
+        };
+        lock.write_all(key.as_bytes())?;
+        lock.commit()?;
+        Ok(key)
+    }
+
+    /**
+     * 'delete_tag'删除标签，返回标签原来指向的key，标签指向的对象不会被删除
+     */
+    pub fn delete_tag(&self, name: &str)-> Result<String> {
+        check_ref_name("tag", name)?;
+        let tag_path = self.tags_path().join(name);
+        let _lock = LockFile::acquire(&tag_path)?;
+        match self.tag_key(name)? {
+            Some(key)=> {
+                fs::remove_file(&tag_path)?;
+                Ok(key)
+            },
+            None=> Err(Error::TagNotFound(name.to_string())),
+        }
+    }
+
+    /**
+     * 'checkout_new_branch'创建一个新分支并切换到这个分支
+     */
+    pub fn checkout_new_branch(&self, branch_name: &str)-> Result<()> {
+        self.create_branch(branch_name)?;
+        self.move_head(&Head::Branch(branch_name.to_string()))
+    }
+
+    /**
+     * 将HEAD指向new_head，并在HEAD的引用日志中记录切换前后的提交
+     */
+    fn move_head(&self, new_head: &Head)-> Result<()> {
+        let head = self.head()?;
+        let old_key = self.head_key(&head)?;
+        self.write_head(new_head)?;
+        let new_key = self.head_key(new_head)?;
+        let [from, to] = [&head, new_head].map(|head| match head {
+            Head::Branch(name)=> name.clone(),
+            Head::Detached(key)=> key.clone(),
+        });
+        self.append_reflog(None, old_key.as_deref(), new_key.as_deref(), &self.identity, &format!("checkout: moving from {from} to {to}"))
+    }
+
+
+    fn get_value_from_key(&self, key: &str)-> Result<Vec<u8>> {
+        // key的长度由仓库的哈希算法决定
+        if !self.hash.is_valid_key(key) {
+            return Err(Error::ObjectNotFound(key.to_string()));
+        }
+        let data = fs::read(self.minigit_path.join("objects").join(&key[0..2]).join(&key[2..])).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound=> Error::ObjectNotFound(key.to_string()),
+            _=> Error::Io(err),
+        })?;
+        self.format.load(data).map_err(|err| err.with_key(key))
+    }
+
+    /**
+     * 'read_object'读取版本表达式rev（例如对象的key、"HEAD"、"HEAD:src"）指向的对象，返回对象的key和解析后的对象
+     */
+    pub fn read_object(&self, rev: &str)-> Result<(String, Object)> {
+        let key = self.resolve_revision(rev)?;
+        // 空的根目录没有保存为对象
+        if key == Commit::EMPTY_TREE {
+            return Ok((key, Object::Tree(Tree::default())));
+        }
+        let object = Object::parse(self.format, self.hash, &self.get_value_from_key(&key)?).map_err(|err| err.with_key(&key))?;
+        Ok((key, object))
+    }
+
+    /**
+     * 'object_size'返回版本表达式rev指向的对象内容的字节数，blob为文件的原始大小，其他对象为类型头之后的内容
+     */
+    pub fn object_size(&self, rev: &str)-> Result<usize> {
+        let key = self.resolve_revision(rev)?;
+        if key == Commit::EMPTY_TREE {
+            return Ok(0);
+        }
+        let value = self.get_value_from_key(&key)?;
+        match ObjectType::of(self.format, &value).map_err(|err| err.with_key(&key))? {
+            (ObjectType::Blob, _)=> Ok(self.read_blob(&key)?.data.len()),
+            (_, body)=> Ok(body.len()),
+        }
+    }
+
+    fn object_type(&self, key: &str)-> Result<ObjectType> {
+        if key == Commit::EMPTY_TREE {
+            return Ok(ObjectType::Tree);
+        }
+        let value = self.get_value_from_key(key)?;
+        Ok(ObjectType::of(self.format, &value).map_err(|err| err.with_key(key))?.0)
+    }
+
+    fn read_blob(&self, key: &str)-> Result<Blob> {
+        Blob::parse(self.format, &self.get_value_from_key(key)?).map_err(|err| err.with_key(key))
+    }
+
+    /**
+     * 读取key对应的tree对象，key为Commit::EMPTY_TREE时返回空的tree
+     */
+    fn read_tree(&self, key: &str)-> Result<Tree> {
+        if key == Commit::EMPTY_TREE {
+            return Ok(Tree::default());
+        }
+        Tree::parse(self.format, self.hash, &self.get_value_from_key(key)?).map_err(|err| err.with_key(key))
+    }
+
+    fn read_commit(&self, key: &str)-> Result<Commit> {
+        Commit::parse(self.format, &self.get_value_from_key(key)?).map_err(|err| err.with_key(key))
+    }
+
+    fn create_file_from_key(&self, index: &mut Index, path: &Path, key: &str, kind: EntryKind)-> Result<()> {
+        // 不能通过原来位置上的符号链接写入链接指向的文件
+        if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            fs::remove_file(path)?;
+        }
+        // 创建文件并将解压的文件内容写入，恢复执行权限，同时记录到暂存区
+        fs::write(path, self.read_blob(key)?.data)?;
+        set_executable(path, kind)?;
+        index.insert(IndexEntry { path: path.to_path_buf(), kind, key: key.to_string(), stat: Stat::from_metadata(&fs::symlink_metadata(path)?) });
+        Ok(())
+    }
+
+    /**
+     * 创建指向blob中保存的路径的符号链接，不支持符号链接的系统上创建内容为该路径的普通文件
+     */
+    fn create_symlink_from_key(&self, index: &mut Index, path: &Path, key: &str)-> Result<()> {
+        let target = self.read_blob(key)?.data;
+        if fs::symlink_metadata(path).is_ok() {
+            fs::remove_file(path)?;
+        }
+        // unix上链接指向的路径可以是任意字节，blob中保存的就是这些字节
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            std::os::unix::fs::symlink(OsStr::from_bytes(&target), path)?;
+        }
+        #[cfg(not(unix))]
+        fs::write(path, &target)?;
+        index.insert(IndexEntry { path: path.to_path_buf(), kind: EntryKind::Symlink, key: key.to_string(), stat: Stat::from_metadata(&fs::symlink_metadata(path)?) });
+        Ok(())
+    }
+
+    /**
+     * 根据tree中的一项在path下创建对应的文件或文件夹
+     */
+    fn create_entry(&self, index: &mut Index, path: &Path, entry: &TreeEntry)-> Result<()> {
+        let entry_path = path.join(&entry.name);
+        match entry.kind {
+            EntryKind::Blob | EntryKind::Executable=> self.create_file_from_key(index, &entry_path, &entry.key, entry.kind),
+            EntryKind::Tree=> self.create_tree_from_key(index, &entry_path, &entry.key),
+            EntryKind::Symlink=> self.create_symlink_from_key(index, &entry_path, &entry.key),
+        }
+    }
+
+    fn create_tree_from_key(&self, index: &mut Index, path: &Path, key: &str)-> Result<()> {
+        fs::create_dir_all(path)?;
+        for entry in self.read_tree(key)?.entries.iter() {
+            self.create_entry(index, path, entry)?;
+        }
+        // 仓库为空时没有根目录的记录
+        if key != Commit::EMPTY_TREE {
+            index.insert(IndexEntry { path: path.to_path_buf(), kind: EntryKind::Tree, key: key.to_string(), stat: Stat::default() });
+        }
+        Ok(())
+    }
+
+    /**
+     * 检查把工作区中的文件从old更新为tree_key对应的tree时会破坏的工作：old和tree中不同、需要重写或者删除的文件上
+     * 已经暂存或者没有暂存的修改，以及与tree中的文件位置冲突的未跟踪文件，两边相同的文件上的修改不受影响
+     * force为false时有这样的路径就返回Error::WouldOverwrite，为true时直接删除冲突的未跟踪文件，修改交给调用者丢弃
+     * 返回检查时工作区的状态
+     */
+    fn check_worktree(&self, old: &BTreeMap<PathBuf, (EntryKind, String)>, tree_key: &str, force: bool)-> Result<Status> {
+        let status = self.status()?;
+        let mut target = BTreeMap::new();
+        self.flatten_tree(&self.root_path, tree_key, &mut target)?;
+        let mut paths = Vec::new();
+        if !force {
+            paths.extend(status.staged.iter().chain(&status.unstaged)
+                               .map(|(name, _)| self.root_path.join(name))
+                               .filter(|path| old.get(path) != target.get(path)));
+        }
+        for name in &status.untracked {
+            // 未跟踪的文件（或文件夹）与目标中的文件重合，或者位于目标中的文件所在的位置之下
+            let path = self.root_path.join(name.trim_end_matches('/'));
+            if !target.keys().any(|file| file.starts_with(&path) || path.starts_with(file)) {
+                continue;
+            }
+            if !force {
+                paths.push(path);
+            }
+            else if fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir()) {
+                fs::remove_dir_all(&path)?;
+            }
+            else {
+                fs::remove_file(&path)?;
+            }
+        }
+        if paths.is_empty() {
+            return Ok(status);
+        }
+        paths.sort();
+        paths.dedup();
+        Err(Error::WouldOverwrite(paths))
+    }
+
+    /**
+     * 删除工作区和暂存区中path处的一项，文件夹中没有被跟踪的文件会保留下来
+     */
+    fn remove_entry(&self, index: &mut Index, path: &Path, kind: EntryKind)-> Result<()> {
+        let metadata = fs::symlink_metadata(path).ok();
+        if kind == EntryKind::Tree {
+            if metadata.is_some_and(|metadata| metadata.is_dir()) {
+                clear_worktree(path, index)?;
+                if path.read_dir()?.next().is_none() {
+                    fs::remove_dir(path)?;
+                }
+            }
+        }
+        else if metadata.is_some_and(|metadata| !metadata.is_dir()) {
+            fs::remove_file(path)?;
+        }
+        self.forget_index(index, path);
+        Ok(())
+    }
+
+    /**
+     * 将暂存区中的文件夹path（key为old_key，None表示暂存区中没有它）和工作区一起更新为new_key对应的tree
+     * 只创建、修改或者删除两个tree中不同的项，key相同的子文件夹整个跳过，没有变化的文件不会被重写
+     */
+    fn checkout_tree(&self, index: &mut Index, path: &Path, old_key: Option<&str>, new_key: &str)-> Result<()> {
+        if old_key == Some(new_key) {
+            return Ok(());
+        }
+        fs::create_dir_all(path)?;
+        let new = self.read_tree(new_key)?;
+        let old = index.children(path).iter().map(|entry| (entry.path.clone(), entry.kind, entry.key.clone())).collect::<Vec<_>>();
+        // 文件和文件夹即使同名也是不同的项
+        let same_name = |x: &Path, x_kind: EntryKind, y: &TreeEntry| x.file_name() == Some(y.name.as_os_str()) && (x_kind == EntryKind::Tree) == (y.kind == EntryKind::Tree);
+        // 先删除目标中没有的项，给同名的新项腾出位置
+        for (old_path, old_kind, _) in &old {
+            if !new.entries.iter().any(|entry| same_name(old_path, *old_kind, entry)) {
+                self.remove_entry(index, old_path, *old_kind)?;
+            }
+        }
+        for entry in &new.entries {
+            let old_entry = old.iter().find(|(old_path, old_kind, _)| same_name(old_path, *old_kind, entry));
+            match entry.kind {
+                EntryKind::Tree=> self.checkout_tree(index, &path.join(&entry.name), old_entry.map(|(_, _, key)| key.as_str()), &entry.key)?,
+                kind=> if old_entry.is_none_or(|(_, old_kind, key)| *old_kind != kind || *key != entry.key) {
+                    self.create_entry(index, path, entry)?;
+                },
+            }
+        }
+        // 仓库为空时没有根目录的记录
+        if new_key == Commit::EMPTY_TREE {
+            index.remove(path);
+        }
+        else {
+            index.insert(IndexEntry { path: path.to_path_buf(), kind: EntryKind::Tree, key: new_key.to_string(), stat: Stat::default() });
+        }
+        Ok(())
+    }
+
+    /**
+     * 用key对应的tree更新整个工作区和暂存区，未跟踪的文件保留下来
+     * force为false时，会破坏没有提交的修改或者未跟踪的文件时返回Error::WouldOverwrite
+     */
+    fn reset_worktree(&self, tree_key: &str, force: bool)-> Result<()> {
+        // 暂存区与目标tree中相同的文件不会被重写
+        let status = self.check_worktree(&index_files(&self.read_index()?), tree_key, force)?;
+        let lock = self.lock_index()?;
+        let mut index = self.read_index()?;
+        // 强制切换时丢弃没有暂存的修改：先从工作区和暂存区中删除这些文件，之后按照目标tree重新创建
+        // 不是强制切换时，剩下的修改都在不会被重写的文件上，保留下来
+        if force {
+            for (name, _) in &status.unstaged {
+                let path = self.root_path.join(name);
+                if let Some(kind) = index.get(&path).map(|entry| entry.kind) {
+                    self.remove_entry(&mut index, &path, kind)?;
+                    self.updata_index(&mut index, &path)?;
+                }
+            }
+        }
+        // 只处理暂存区与目标tree中不同的路径
+        let old_key = index.get(&self.root_path).map(|entry| entry.key.clone());
+        self.checkout_tree(&mut index, &self.root_path, old_key.as_deref(), tree_key)?;
+        self.write_index(lock, &index)
+    }
+
+    /**
+     * 把tree中key对应的一项（类型为kind）记录到暂存区的path处，不修改工作区
+     * 与old中的记录完全相同的文件沿用原来的文件状态，这样没有修改的文件不需要重新哈希
+     */
+    fn read_tree_into_index(&self, index: &mut Index, old: &Index, path: &Path, kind: EntryKind, key: &str)-> Result<()> {
+        if kind != EntryKind::Tree {
+            let stat = old.get(path).filter(|entry| entry.kind == kind && entry.key == key).map(|entry| entry.stat).unwrap_or_default();
+            index.insert(IndexEntry { path: path.to_path_buf(), kind, key: key.to_string(), stat });
+            return Ok(());
+        }
+        for entry in self.read_tree(key)?.entries {
+            self.read_tree_into_index(index, old, &path.join(&entry.name), entry.kind, &entry.key)?;
+        }
+        // 仓库为空时没有根目录的记录
+        if key != Commit::EMPTY_TREE {
+            index.insert(IndexEntry { path: path.to_path_buf(), kind: EntryKind::Tree, key: key.to_string(), stat: Stat::default() });
+        }
+        Ok(())
+    }
+
+    /**
+     * 在key对应的tree中寻找仓库中的路径path对应的一项，返回它的类型和key
+     */
+    fn find_tree_entry(&self, tree_key: &str, path: &Path)-> Result<Option<(EntryKind, String)>> {
+        let relative = path.strip_prefix(&self.root_path).map_err(|_| Error::OutsideRepository(path.to_path_buf()))?;
+        let mut found = (EntryKind::Tree, tree_key.to_string());
+        for name in relative.iter() {
+            if found.0 != EntryKind::Tree {
+                return Ok(None);
+            }
+            match self.read_tree(&found.1)?.entries.into_iter().find(|entry| entry.name == name) {
+                None=> return Ok(None),
+                Some(entry)=> found = (entry.kind, entry.key),
+            }
+        }
+        Ok(Some(found))
+    }
+
+    /**
+     * 'resolve_revision'将版本表达式（见'Revision'）转化为它指向的对象的key，例如"master~2"、"v1.0^2"、"HEAD@{1}"、"HEAD:src"
+     * 名字按照HEAD、分支、标签、完整的key、缩写的key的顺序查找，缩写的key至少4位，对应多个对象时返回Error::AmbiguousRevision
+     * # 示例
+     * ```
+        let path = std::env::temp_dir().join("minigit-doc-revision");
+        let repository = minigit::Repository::init(&path).unwrap();
+        std::fs::write(path.join("a.txt"), "a").unwrap();
+        repository.add(&["a.txt"]).unwrap();
+        let first = repository.commit("me", "first").unwrap();
+        repository.commit("me", "second").unwrap();
+        assert_eq!(repository.resolve_revision("HEAD~1").unwrap(), first);
+        assert_eq!(repository.resolve_revision(&first[..7]).unwrap(), first);
+        assert_eq!(repository.resolve_revision("master@{1}").unwrap(), first);
+        assert!(repository.resolve_revision("HEAD:a.txt").is_ok());
+     * ```
+     */
+    pub fn resolve_revision(&self, text: &str)-> Result<String> {
+        let unknown = || Error::UnknownRevision(text.to_string());
+        let revision = Revision::parse(text)?;
+        let mut key = match revision.reflog {
+            Some(n)=> self.reflog_key(&revision.name, n)?.ok_or_else(unknown)?,
+            None=> self.resolve_name(&revision.name)?.ok_or_else(unknown)?,
+        };
+        for step in &revision.steps {
+            let commit_key = self.peel(text, &key, ObjectType::Commit)?;
+            key = match *step {
+                RevisionStep::Ancestor(n)=> {
+                    let mut key = commit_key;
+                    for _ in 0..n {
+                        key = self.read_commit(&key)?.parents.into_iter().next().ok_or_else(unknown)?;
+                    }
+                    key
+                },
+                RevisionStep::Parent(0)=> commit_key,
+                RevisionStep::Parent(n)=> self.read_commit(&commit_key)?.parents.into_iter().nth(n - 1).ok_or_else(unknown)?,
+            };
+        }
+        if let Some(path) = &revision.path {
+            let tree_key = self.peel(text, &key, ObjectType::Tree)?;
+            key = self.find_tree_entry(&tree_key, &self.root_path.join(path))?.ok_or_else(unknown)?.1;
+        }
+        Ok(key)
+    }
+
+    /**
+     * 查找版本表达式中的名字，找不到时返回None
+     */
+    fn resolve_name(&self, name: &str)-> Result<Option<String>> {
+        if name == "HEAD" {
+            return Ok(Some(self.head_commit()?));
+        }
+        if name == "ORIG_HEAD" || name == "MERGE_HEAD" {
+            let path = self.minigit_path.join(name);
+            return if path.is_file() { Ok(Some(fs::read_to_string(path)?.trim_end().to_string())) } else { Ok(None) };
+        }
+        if let Some(key) = self.branch_commit(name)? {
+            return Ok(Some(key));
+        }
+        if let Some(key) = self.tag_key(name)? {
+            return Ok(Some(key));
+        }
+        if self.hash.is_valid_key(name) {
+            return Ok(self.get_value_from_key(name).is_ok().then(|| name.to_string()));
+        }
+        self.expand_short_key(name)
+    }
+
+    /**
+     * 在objects中寻找以name开头的key，name至少4位，对应多个对象时返回Error::AmbiguousRevision
+     */
+    fn expand_short_key(&self, name: &str)-> Result<Option<String>> {
+        let is_hex = name.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+        if name.len() < 4 || name.len() > self.hash.hex_len() || !is_hex {
+            return Ok(None);
+        }
+        let dir = self.minigit_path.join("objects").join(&name[..2]);
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+        let mut found = Vec::new();
+        for entry in dir.read_dir()? {
+            let rest = entry?.file_name().to_string_lossy().into_owned();
+            if rest.starts_with(&name[2..]) {
+                found.push(format!("{}{rest}", &name[..2]));
+            }
+        }
+        match found.len() {
+            0=> Ok(None),
+            1=> Ok(found.pop()),
+            _=> Err(Error::AmbiguousRevision(name.to_string())),
+        }
+    }
+
+    /**
+     * 引用name（为空时为当前分支，"HEAD"或者分离状态时为HEAD）的引用日志中第n条记录（最新的为0）之后指向的提交
+     */
+    fn reflog_key(&self, name: &str, n: usize)-> Result<Option<String>> {
+        let branch_name = match (name, self.head()?) {
+            ("", Head::Branch(branch_name))=> Some(branch_name),
+            ("", Head::Detached(_)) | ("HEAD", _)=> None,
+            (name, _)=> Some(name.to_string()),
+        };
+        let entry = self.reflog(branch_name.as_deref())?.into_iter().nth(n);
+        // 引用被删除的记录没有指向任何提交
+        Ok(entry.map(|entry| entry.new_key).filter(|key| key.bytes().any(|b| b != b'0')))
+    }
+
+    /**
+     * 'resolve_commit'将版本表达式转化为提交的key，附注标签会被解开为它指向的提交
+     */
+    fn resolve_commit(&self, name: &str)-> Result<String> {
+        let key = self.resolve_revision(name)?;
+        self.peel(name, &key, ObjectType::Commit)
+    }
+
+    /**
+     * 逐层解开附注标签（标签也可以指向另一个标签），需要tree时再取出提交的tree，直到得到object_type类型的对象的key
+     * 无法得到这种对象时返回Error::UnknownRevision
+     */
+    fn peel(&self, name: &str, key: &str, object_type: ObjectType)-> Result<String> {
+        let mut key = key.to_string();
+        loop {
+            if key == Commit::EMPTY_TREE && object_type == ObjectType::Tree {
+                return Ok(key);
+            }
+            let value = self.get_value_from_key(&key)?;
+            let found = ObjectType::of(self.format, &value).map_err(|err| err.with_key(&key))?.0;
+            key = match found {
+                found if found == object_type=> return Ok(key),
+                ObjectType::Tag=> Tag::parse(self.format, &value).map_err(|err| err.with_key(&key))?.object,
+                ObjectType::Commit if object_type == ObjectType::Tree=> self.read_commit(&key)?.tree,
+                _=> return Err(Error::UnknownRevision(name.to_string())),
+            };
+        }
+    }
+
+    /**
+     * HEAD指向的提交，指向的分支还没有提交时返回Error::NoCommits
+     */
+    fn head_commit(&self)-> Result<String> {
+        match self.head()? {
+            Head::Branch(name)=> self.branch_commit(&name)?.ok_or(Error::NoCommits(name)),
+            Head::Detached(key)=> Ok(key),
+        }
+    }
+
+    /**
+     * 'reset'将当前分支（分离状态时为HEAD）移动到提交commit（分支名字、标签名字或者提交的key，None表示当前提交），返回新的提交的key
+     * 按照mode决定是否同时重建暂存区和工作区，移动之前的提交记录在'.minigit/ORIG_HEAD'中，可以再用'reset'回到这个提交
+     */
+    pub fn reset(&self, commit: Option<&str>, mode: ResetMode)-> Result<String> {
+        let head = self.head()?;
+        let mut ref_lock = LockFile::acquire(&self.head_ref_path(&head))?;
+        let old_key = self.head_commit()?;
+        let new_key = match commit {
+            None=> old_key.clone(),
+            Some(name)=> self.resolve_commit(name)?,
+        };
+        let tree_key = self.get_tree_key_from_commit(&new_key)?;
+        match mode {
+            ResetMode::Soft=> {},
+            ResetMode::Mixed=> {
+                let lock = self.lock_index()?;
+                let mut index = Index::default();
+                self.read_tree_into_index(&mut index, &self.read_index()?, &self.root_path, EntryKind::Tree, &tree_key)?;
+                self.write_index(lock, &index)?;
+            },
+            // 与git相同，'--hard'会丢弃所有修改并覆盖冲突的未跟踪文件
+            ResetMode::Hard=> self.reset_worktree(&tree_key, true)?,
+        }
+        // 重建了暂存区时，没有完成的合并也被放弃
+        let merge_head = self.minigit_path.join("MERGE_HEAD");
+        if mode != ResetMode::Soft && merge_head.is_file() {
+            fs::remove_file(merge_head)?;
+            self.write_conflicts(&BTreeSet::new())?;
+        }
+        self.write_ref(&self.minigit_path.join("ORIG_HEAD"), &old_key)?;
+        ref_lock.write_all(new_key.as_bytes())?;
+        ref_lock.commit()?;
+        let reason = format!("reset: moving to {}", commit.unwrap_or("HEAD"));
+        self.log_head_update(&head, Some(&old_key), Some(&new_key), &self.identity, &reason)?;
+        Ok(new_key)
+    }
+
+    /**
+     * 'reset_paths'用提交commit（None表示当前提交）中的版本替换暂存区中paths下的记录，不移动分支也不修改工作区
+     * 提交中不存在的路径从暂存区中删除，常用于取消暂存
+     */
+    pub fn reset_paths<P: AsRef<Path>>(&self, commit: Option<&str>, paths: &[P])-> Result<()> {
+        let commit_key = match commit {
+            None=> self.head_commit()?,
+            Some(name)=> self.resolve_commit(name)?,
+        };
+        let tree_key = self.get_tree_key_from_commit(&commit_key)?;
+        let lock = self.lock_index()?;
+        let mut index = self.read_index()?;
+        let old = index.clone();
+        for path in paths {
+            let path = self.resolve(path.as_ref())?;
+            let found = self.find_tree_entry(&tree_key, &path)?;
+            if found.is_none() && index.get(&path).is_none() {
+                continue;
+            }
+            self.forget_index(&mut index, &path);
+            if let Some((kind, key)) = found {
+                self.read_tree_into_index(&mut index, &old, &path, kind, &key)?;
+            }
+            self.updata_index(&mut index, &path)?;
+        }
+        self.write_index(lock, &index)
+    }
+
+    /**
+     * 'restore'用暂存区或者提交source中的版本重写工作区中的paths，不影响其他文件
+     * staged为true时改为用source（None表示当前提交）中的版本替换暂存区中的记录，工作区不变，与'reset_paths'相同
+     * 从暂存区恢复时会更新恢复的文件在暂存区中的文件状态，从提交恢复时暂存区不变
+     */
+    pub fn restore<P: AsRef<Path>>(&self, source: Option<&str>, staged: bool, paths: &[P])-> Result<()> {
+        if staged {
+            return self.reset_paths(source, paths);
+        }
+        let tree_key = match source {
+            None=> None,
+            Some(name)=> Some(self.get_tree_key_from_commit(&self.resolve_commit(name)?)?),
+        };
+        let lock = self.lock_index()?;
+        let mut index = self.read_index()?;
+        for path in paths {
+            let path = self.resolve(path.as_ref())?;
+            let found = match &tree_key {
+                Some(tree_key)=> self.find_tree_entry(tree_key, &path)?,
+                None=> index.get(&path).map(|entry| (entry.kind, entry.key.clone())),
+            };
+            let (kind, key) = found.ok_or_else(|| Error::InvalidPath(path.clone()))?;
+            let (parent, name) = match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name))=> (parent, name.to_os_string()),
+                _=> return Err(Error::InvalidPath(path.clone())),
+            };
+            fs::create_dir_all(parent)?;
+            // 文件夹所在的位置上原来是文件或者符号链接时先删除它
+            if kind == EntryKind::Tree && fs::symlink_metadata(&path).is_ok_and(|metadata| !metadata.is_dir()) {
+                fs::remove_file(&path)?;
+            }
+            let entry = TreeEntry { kind, key, name };
+            match tree_key {
+                Some(_)=> self.create_entry(&mut Index::default(), parent, &entry)?,
+                None=> self.create_entry(&mut index, parent, &entry)?,
+            }
+        }
+        self.write_index(lock, &index)
+    }
+
+    /**
+     * 'checkout'切换到分支branch_name，并用该分支的最新提交重建工作区和暂存区，未跟踪的文件保留下来
+     * branch_name不是分支而是其他版本表达式（提交的key、标签、"HEAD~1"等）时进入分离状态，HEAD直接指向它指向的提交
+     * 有没有提交的修改，或者未跟踪的文件与该分支中的文件冲突时，返回Error::WouldOverwrite并且不做任何修改
+     */
+    pub fn checkout(&self, branch_name: &str)-> Result<()> {
+        self.switch_branch(branch_name, false)
+    }
+
+    /**
+     * 'force_checkout'与'checkout'相同，但是会丢弃没有提交的修改，并删除与该分支中的文件冲突的未跟踪文件
+     */
+    pub fn force_checkout(&self, branch_name: &str)-> Result<()> {
+        self.switch_branch(branch_name, true)
+    }
+
+    fn switch_branch(&self, branch_name: &str, force: bool)-> Result<()> {
+        let head = self.head()?;
+        let (target, commit_key) = match self.branch_commit(branch_name)? {
+            Some(key)=> (Head::Branch(branch_name.to_string()), key),
+            None=> match self.resolve_commit(branch_name) {
+                Ok(key)=> (Head::Detached(key.clone()), key),
+                Err(Error::UnknownRevision(_))=> return Err(Error::BranchNotFound(branch_name.to_string())),
+                Err(err)=> return Err(err),
+            },
+        };
+        if head == target {
+            return Ok(());
+        }
+        // 指向同一个提交时不用修改工作区，没有提交的修改会保留下来
+        if self.head_key(&head)?.as_ref() != Some(&commit_key) {
+            // get root_tree_key
+            let tree_key = self.get_tree_key_from_commit(&commit_key)?;
+            self.reset_worktree(&tree_key, force)?;
+        }
+        // move HEAD ptr
+        self.move_head(&target)
+    }
+
+
+    fn get_tree_key_from_commit(&self, commit_key: &str)-> Result<String> {
+        Ok(self.read_commit(commit_key)?.tree)
+    }
+
+    /**
+     * 'get_parent_commit'返回提交的全部父提交，根提交没有父提交，合并提交有多个父提交
+     */
+    fn get_parent_commit(&self, commit_key: &str)-> Result<Vec<String>> {
+        Ok(self.read_commit(commit_key)?.parents)
+    }
+
+    /**
+     * 'get_generation'计算提交的代数：根提交为1，其他提交为父提交中最大代数加1，结果缓存在cache中
+     */
+    fn get_generation(&self, commit_key: &str, cache: &mut HashMap<String, u64>)-> Result<u64> {
+        // 使用栈代替递归，避免历史很长时栈溢出
+        let mut stack = vec![commit_key.to_string()];
+        while let Some(key) = stack.last().cloned() {
+            if cache.contains_key(&key) {
+                stack.pop();
+                continue;
+            }
+            let parents = self.get_parent_commit(&key)?;
+            let unknown = parents.iter().filter(|p| !cache.contains_key(*p)).cloned().collect::<Vec<String>>();
+            if unknown.is_empty() {
+                let generation = parents.iter().map(|p| cache[p]).max().unwrap_or(0) + 1;
+                cache.insert(key, generation);
+                stack.pop();
+            }
+            else {
+                stack.extend(unknown);
+            }
+        }
+        Ok(cache[commit_key])
+    }
+
+    fn read_log_entry(&self, commit_key: &str)-> Result<LogEntry> {
+        let commit = self.read_commit(commit_key)?;
+        Ok(LogEntry {
+            key: commit_key.to_string(),
+            parents: commit.parents,
+            author: commit.author,
+            datetime: commit.datetime,
+            message: commit.message,
+        })
+    }
+
+    /**
+     * 'log'从分支branch_name（也可以是其他版本表达式，为None时为HEAD）指向的提交开始，沿着parent依次读取最多limit条提交记录
+     * 与git相同按照提交时间从新到旧输出，时间相同时先遇到的提交在前，只读取输出的提交和它们的父提交
+     */
+    pub fn log(&self, branch_name: Option<&str>, limit: Option<usize>)-> Result<Vec<LogEntry>> {
+        let key = match branch_name {
+            None=> self.head_commit()?,
+            Some(name)=> self.resolve_commit(name)?,
+        };
+        if limit == Some(0) {
+            return Ok(Vec::new());
+        }
+        // found按照遇到的顺序保存读取过的提交，heap中保存它们的提交时间和在found中的下标
+        let mut found = vec![self.read_log_entry(&key)?];
+        let mut heap = BinaryHeap::new();
+        let mut visited = HashSet::new();
+        heap.push((parse_datetime(&found[0].datetime), Reverse(0)));
+        visited.insert(key);
+        let mut re = Vec::new();
+        while let Some((_, Reverse(i))) = heap.pop() {
+            re.push(found[i].clone());
+            // 已经得到limit条记录时不再读取父提交
+            if limit.is_some_and(|n| re.len() >= n) {
+                break;
+            }
+            for parent in found[i].parents.clone() {
+                if visited.insert(parent.clone()) {
+                    let entry = self.read_log_entry(&parent)?;
+                    heap.push((parse_datetime(&entry.datetime), Reverse(found.len())));
+                    found.push(entry);
+                }
+            }
+        }
+        Ok(re)
+    }
+
+    /**
+     * 'reflog'返回分支branch_name（None表示HEAD）的引用日志，最新的记录在前，已经删除的分支仍然可以查看
+     */
+    pub fn reflog(&self, branch_name: Option<&str>)-> Result<Vec<ReflogEntry>> {
+        let path = self.reflog_path(branch_name);
+        if let Some(name) = branch_name {
+            check_ref_name("branch", name)?;
+            if !path.is_file() && !self.branch_path(name).is_file() {
+                return Err(Error::BranchNotFound(name.to_string()));
+            }
+        }
+        let mut entries = reflog::read(&path)?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /**
+     * 'find_merge_bases'寻找两个提交的全部最近公共祖先
+     * 按照代数从大到小遍历提交，分别标记从commit_key1、commit_key2可以到达的提交，
+     * 第一次被两边同时标记的提交为候选，它的祖先都被标记为过时，遍历到所有待处理的提交都过时为止
+     */
+    pub(crate) fn find_merge_bases(&self, commit_key1: &str, commit_key2: &str)-> Result<Vec<String>> {
+        const PARENT1: u8 = 1;
+        const PARENT2: u8 = 2;
+        const STALE: u8 = 4;
+        if commit_key1 == commit_key2 {
+            return Ok(vec![commit_key1.to_string()]);
+        }
+        let mut generations = HashMap::new();
+        let mut flags: HashMap<String, u8> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        for (key, flag) in [(commit_key1, PARENT1), (commit_key2, PARENT2)] {
+            flags.insert(key.to_string(), flag);
+            heap.push((self.get_generation(key, &mut generations)?, key.to_string()));
+        }
+        let mut re = Vec::new();
+        while heap.iter().any(|(_, key)| flags[key] & STALE == 0) {
+            let (_, key) = heap.pop().unwrap();
+            let mut flag = flags[&key];
+            if flag == PARENT1 | PARENT2 {
+                if !re.contains(&key) {
+                    re.push(key.clone());
+                }
+                flag |= STALE;
+            }
+            for parent in self.get_parent_commit(&key)? {
+                let parent_flag = flags.entry(parent.clone()).or_insert(0);
+                if *parent_flag & flag == flag {
+                    continue;
+                }
+                *parent_flag |= flag;
+                heap.push((self.get_generation(&parent, &mut generations)?, parent));
+            }
+        }
+        // 被其他候选到达过的候选不是最近的公共祖先
+        Ok(re.into_iter().filter(|key| flags[key] & STALE == 0).collect())
+    }
+
+    /**
+     * 'merge_base_tree'返回三路合并时作为共同祖先的tree，bases为两个提交的全部最近公共祖先（至少一个）
+     * 只有一个时就是它的tree；交叉合并后会有多个，与git的recursive策略相同，先依次合并这些公共祖先，以合并的结果作为共同祖先，
+     * 其中的冲突保留为标出冲突后的内容。合并结果保存为不被任何引用指向的提交，以便继续寻找它与下一个公共祖先的共同祖先
+     */
+    fn merge_base_tree(&self, bases: &[String])-> Result<String> {
+        let mut key = bases[0].clone();
+        for other in &bases[1..] {
+            let mut trees_key = vec![self.get_tree_key_from_commit(&key)?, self.get_tree_key_from_commit(other)?];
+            let inner_bases = self.find_merge_bases(&key, other)?;
+            if !inner_bases.is_empty() {
+                trees_key.push(self.merge_base_tree(&inner_bases)?);
+            }
+            let tree = self.merge_tree("merged common ancestors", &self.root_path, &trees_key, &mut Vec::new())?;
+            let commit = Commit {
+                parents: vec![key, other.clone()],
+                author: self.identity.clone(),
+                datetime: Utc::now().to_string(),
+                message: "merged common ancestors".to_string(),
+                tree,
+            };
+            key = self.save_value(&commit.serialize(self.format))?;
+        }
+        self.get_tree_key_from_commit(&key)
+    }
+
+
+    /**
+     * 'diff'比较两个blob对象的内容，返回带有context行上下文的unified diff区块
+     * key1和key2也可以是指向blob的版本表达式，例如"HEAD~1:a.txt"
+     */
+    pub fn diff(&self, key1: &str, key2: &str, context: usize)-> Result<Vec<u8>> {
+        let old = self.read_blob(&self.resolve_revision(key1)?)?.data;
+        let new = self.read_blob(&self.resolve_revision(key2)?)?.data;
+        Ok(diff::unified_diff(&old, &new, context))
+    }
+
+    /**
+     * 'diff_revisions'比较两个版本表达式指向的提交（或者tree）中的文件，输出格式与'diff_worktree'相同
+     * paths为空时比较全部文件，否则只比较这些路径下的文件；两个版本表达式都指向blob时直接比较它们的内容
+     */
+    pub fn diff_revisions<P: AsRef<Path>>(&self, rev1: &str, rev2: &str, paths: &[P], context: usize)-> Result<Vec<u8>> {
+        let paths = paths.iter().map(|p| self.resolve(p.as_ref())).collect::<Result<Vec<PathBuf>>>()?;
+        let keys = [self.resolve_revision(rev1)?, self.resolve_revision(rev2)?];
+        let mut out = Vec::new();
+        if self.object_type(&keys[0])? == ObjectType::Blob && self.object_type(&keys[1])? == ObjectType::Blob {
+            out.extend_from_slice(format!("--- a/{rev1}\n+++ b/{rev2}\n").as_bytes());
+            out.append(&mut self.diff(&keys[0], &keys[1], context)?);
+            return Ok(out);
+        }
+        self.diff_trees(&self.peel(rev1, &keys[0], ObjectType::Tree)?, &self.peel(rev2, &keys[1], ObjectType::Tree)?, &paths, context)
+    }
+
+    /**
+     * 'diff_commit'比较提交rev与它的第一个父提交，根提交与空的tree比较
+     */
+    pub fn diff_commit(&self, rev: &str, context: usize)-> Result<Vec<u8>> {
+        let commit = self.read_commit(&self.resolve_commit(rev)?)?;
+        let old_tree_key = match commit.parents.first() {
+            Some(parent)=> self.get_tree_key_from_commit(parent)?,
+            None=> Commit::EMPTY_TREE.to_string(),
+        };
+        self.diff_trees(&old_tree_key, &commit.tree, &[], context)
+    }
+
+    fn diff_trees(&self, old_tree_key: &str, new_tree_key: &str, paths: &[PathBuf], context: usize)-> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut old_files = BTreeMap::new();
+        let mut new_files = BTreeMap::new();
+        self.flatten_tree(&self.root_path, old_tree_key, &mut old_files)?;
+        self.flatten_tree(&self.root_path, new_tree_key, &mut new_files)?;
+        let all_paths = old_files.keys().chain(new_files.keys()).collect::<BTreeSet<_>>();
+        for path in all_paths {
+            if !paths.is_empty() && !paths.iter().any(|p| path.starts_with(p)) {
+                continue;
+            }
+            let (old, new) = (old_files.get(path), new_files.get(path));
+            if old == new {
+                continue;
+            }
+            let read = |entry: Option<&(EntryKind, String)>| entry.map(|(kind, key)| self.read_blob(key).map(|blob| (*kind, blob.data))).transpose();
+            write_file_diff(&mut out, &relative_name(&self.root_path, path), read(old)?, read(new)?, context);
+        }
+        Ok(out)
+    }
+
+    /**
+     * 'diff_revision_worktree'比较版本表达式rev指向的提交（或者tree）与工作区中的文件，与"git diff <rev>"相同
+     * 只比较rev或者暂存区中有的文件，没有被跟踪的文件不会出现；paths为空时比较全部文件，否则只比较这些路径下的文件
+     */
+    pub fn diff_revision_worktree<P: AsRef<Path>>(&self, rev: &str, paths: &[P], context: usize)-> Result<Vec<u8>> {
+        let paths = paths.iter().map(|p| self.resolve(p.as_ref())).collect::<Result<Vec<PathBuf>>>()?;
+        let tree_key = self.peel(rev, &self.resolve_revision(rev)?, ObjectType::Tree)?;
+        let mut old_files = BTreeMap::new();
+        self.flatten_tree(&self.root_path, &tree_key, &mut old_files)?;
+        let index = self.read_index()?;
+        let tracked = index_files(&index);
+        let mut out = Vec::new();
+        for path in old_files.keys().chain(tracked.keys()).collect::<BTreeSet<_>>() {
+            if !paths.is_empty() && !paths.iter().any(|p| path.starts_with(p)) {
+                continue;
+            }
+            let old = old_files.get(path);
+            let metadata = fs::symlink_metadata(path).ok().filter(|metadata| !metadata.is_dir());
+            // 文件状态与暂存区相同、暂存区又与rev相同时不用读取文件内容
+            if let (Some(metadata), Some((kind, key))) = (&metadata, old) {
+                if index.cached_key(path, metadata) == Some(key.as_str()) && index.get(path).is_some_and(|entry| entry.kind == *kind) {
+                    continue;
+                }
+            }
+            let old = old.map(|(kind, key)| self.read_blob(key).map(|blob| (*kind, blob.data))).transpose()?;
+            let new = match metadata.as_ref().and_then(|metadata| entry_kind(metadata).map(|kind| (kind, metadata))) {
+                Some((kind, metadata)) if metadata.file_type().is_symlink()=> Some((kind, fs::read_link(path)?.into_os_string().into_encoded_bytes())),
+                Some((kind, _))=> Some((kind, fs::read(path)?)),
+                None=> None,
+            };
+            if old != new {
+                write_file_diff(&mut out, &relative_name(&self.root_path, path), old, new, context);
+            }
+        }
+        Ok(out)
+    }
+
+    /**
+     * 'diff_worktree'比较工作区与暂存区（index）中的文件，paths为空时比较全部文件，否则只比较这些路径下的文件
+     */
+    pub fn diff_worktree<P: AsRef<Path>>(&self, paths: &[P], context: usize)-> Result<Vec<u8>> {
+        let paths = paths.iter().map(|p| self.resolve(p.as_ref())).collect::<Result<Vec<PathBuf>>>()?;
+        let mut out = Vec::new();
+        let index = self.read_index()?;
+        for IndexEntry { path, kind, key, .. } in index.entries() {
+            if *kind == EntryKind::Tree {
+                continue;
+            }
+            if !paths.is_empty() && !paths.iter().any(|p| path.starts_with(p)) {
+                continue;
+            }
+            let metadata = fs::symlink_metadata(path).ok().filter(|metadata| !metadata.is_dir());
+            let exists = metadata.is_some();
+            // 文件状态没有变化时不用读取文件内容
+            if metadata.as_ref().is_some_and(|metadata| index.cached_key(path, metadata) == Some(key)) {
+                continue;
+            }
+            let name = relative_name(&self.root_path, path);
+            let old = self.read_blob(key)?.data;
+            // 符号链接比较的是它指向的路径
+            let new = match &metadata {
+                Some(metadata) if metadata.file_type().is_symlink()=> fs::read_link(path)?.into_os_string().into_encoded_bytes(),
+                Some(_)=> fs::read(path)?,
+                None=> Vec::new(),
+            };
+            let new_kind = metadata.as_ref().and_then(entry_kind);
+            let mode_changed = new_kind.is_some_and(|new_kind| new_kind != *kind);
+            if exists && old == new && !mode_changed {
+                continue;
+            }
+            out.extend_from_slice(format!("diff --minigit a/{name} b/{name}\n").as_bytes());
+            // 文件模式改变（例如增加了执行权限）时先输出新旧模式
+            if let Some(new_kind) = new_kind.filter(|_| mode_changed) {
+                out.extend_from_slice(format!("old mode {}\nnew mode {}\n", kind.git_mode(), new_kind.git_mode()).as_bytes());
+            }
+            if !exists {
+                out.extend_from_slice(format!("deleted file\n--- a/{name}\n+++ /dev/null\n").as_bytes());
+            }
+            else if old != new {
+                out.extend_from_slice(format!("--- a/{name}\n+++ b/{name}\n").as_bytes());
+            }
+            out.append(&mut diff::unified_diff(&old, &new, context));
+        }
+        Ok(out)
+    }
+
+
+    /**
+     * 'ls_files'按路径顺序列出暂存区中的文件（不包括文件夹），paths为空时列出全部文件，否则只列出这些路径下的文件
+     */
+    pub fn ls_files<P: AsRef<Path>>(&self, paths: &[P])-> Result<Vec<ListEntry>> {
+        let paths = paths.iter().map(|p| self.resolve(p.as_ref())).collect::<Result<Vec<PathBuf>>>()?;
+        let index = self.read_index()?;
+        let entries = index.entries().iter()
+            .filter(|entry| entry.kind != EntryKind::Tree)
+            .filter(|entry| paths.is_empty() || paths.iter().any(|p| entry.path.starts_with(p)))
+            .map(|entry| ListEntry { path: relative_name(&self.root_path, &entry.path), kind: entry.kind, key: entry.key.clone() });
+        Ok(entries.collect())
+    }
+
+    /**
+     * 'ls_tree'列出版本表达式rev指向的提交（或者tree）中的项，path不为None时列出其中这个文件夹里的项（path为文件时只有它自己）
+     * recursive为true时递归进入子文件夹，只列出文件
+     */
+    pub fn ls_tree(&self, rev: &str, path: Option<&Path>, recursive: bool)-> Result<Vec<ListEntry>> {
+        let tree_key = self.peel(rev, &self.resolve_revision(rev)?, ObjectType::Tree)?;
+        let path = match path {
+            Some(path)=> self.resolve(path)?,
+            None=> self.root_path.clone(),
+        };
+        let mut re = Vec::new();
+        match self.find_tree_entry(&tree_key, &path)? {
+            None=> {},
+            Some((EntryKind::Tree, key))=> self.list_tree(&key, &path, recursive, &mut re)?,
+            Some((kind, key))=> re.push(ListEntry { path: relative_name(&self.root_path, &path), kind, key }),
+        }
+        Ok(re)
+    }
+
+    /**
+     * 按照tree中保存的顺序列出key对应的tree里的项，path为这个tree在仓库中的路径
+     */
+    fn list_tree(&self, key: &str, path: &Path, recursive: bool, re: &mut Vec<ListEntry>)-> Result<()> {
+        for entry in self.read_tree(key)?.entries {
+            let file_path = path.join(&entry.name);
+            if recursive && entry.kind == EntryKind::Tree {
+                self.list_tree(&entry.key, &file_path, recursive, re)?;
+            }
+            else {
+                re.push(ListEntry { path: relative_name(&self.root_path, &file_path), kind: entry.kind, key: entry.key });
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * 将key对应的tree对象中全部的文件展开到map中，map的键为文件路径，值为文件的类型和blob的key
+     */
+    fn flatten_tree(&self, path: &Path, key: &str, map: &mut BTreeMap<PathBuf, (EntryKind, String)>)-> Result<()> {
+        for entry in self.read_tree(key)?.entries {
+            let file_path = path.join(&entry.name);
+            match entry.kind {
+                EntryKind::Tree=> self.flatten_tree(&file_path, &entry.key, map)?,
+                kind=> { map.insert(file_path, (kind, entry.key)); },
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * 没有被跟踪的文件夹path中是否有没有被忽略的文件
+     */
+    fn has_unignored(&self, path: &Path, ignore: &Ignore)-> Result<bool> {
+        for entry in path.read_dir()? {
+            let entry = entry?;
+            let is_dir = entry.file_type()?.is_dir();
+            if ignore.is_ignored(&entry.path(), is_dir)? {
+                continue;
+            }
+            if !is_dir || self.has_unignored(&entry.path(), ignore)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /**
+     * 遍历工作区中path下的文件，计算每个已保存文件的key，并记录没有保存到暂存区也没有被忽略的文件
+     */
+    fn scan_worktree(&self, path: &Path, index: &Index, ignore: &Ignore, re: &mut Status)-> Result<()> {
+        let root_path = &self.root_path;
+        let mut entries = path.read_dir()?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let child_path = entry.path();
+            if entry.file_name() == ".minigit" {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if self.is_skipped(index, ignore, &child_path, metadata.is_dir())? {
+                continue;
+            }
+            let tracked = index.get(&child_path);
+            if metadata.is_dir() {
+                if tracked.is_some_and(|entry| entry.kind == EntryKind::Tree) {
+                    self.scan_worktree(&child_path, index, ignore, re)?;
+                }
+                else if self.has_unignored(&child_path, ignore)? {
+                    re.untracked.push(format!("{}/", relative_name(root_path, &child_path)));
+                }
+            }
+            else if let Some(kind) = entry_kind(&metadata) {
+                match tracked.filter(|entry| entry.kind != EntryKind::Tree) {
+                    None=> re.untracked.push(relative_name(root_path, &child_path)),
+                    Some(tracked)=> {
+                        // 文件类型或者执行权限改变（例如普通文件变为符号链接）也是修改，文件状态没有变化时直接使用暂存区中的key
+                        if tracked.kind != kind || (index.cached_key(&child_path, &metadata).is_none()
+                            && self.hash.hash(&self.file_value(&child_path, kind)?) != tracked.key) {
+                            re.unstaged.push((relative_name(root_path, &child_path), FileStatus::Modified));
+                        }
+                    },
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * 'status'比较当前分支最新提交的tree、暂存区和工作区，只计算key而不会写入新的对象
+     */
+    pub fn status(&self)-> Result<Status> {
+        let root_path = &self.root_path;
+        let now_head = self.head()?;
+        let mut re = match &now_head {
+            Head::Branch(name)=> Status { branch: name.clone(), ..Default::default() },
+            Head::Detached(key)=> Status { branch: key.clone(), detached: true, ..Default::default() },
+        };
+        // HEAD指向的提交中的文件
+        let mut head = BTreeMap::new();
+        if let Some(commit_key) = self.head_key(&now_head)? {
+            let tree_key = self.get_tree_key_from_commit(&commit_key)?;
+            self.flatten_tree(root_path, &tree_key, &mut head)?;
+        }
+        // 暂存区中的文件
+        let index = self.read_index()?;
+        let files = index_files(&index);
+        for (path, file) in &files {
+            match head.get(path) {
+                None=> re.staged.push((relative_name(root_path, path), FileStatus::New)),
+                Some(head_file)=> if head_file != file {
+                    re.staged.push((relative_name(root_path, path), FileStatus::Modified));
+                },
+            }
+            if !fs::symlink_metadata(path).is_ok_and(|metadata| !metadata.is_dir()) {
+                re.unstaged.push((relative_name(root_path, path), FileStatus::Deleted));
+            }
+        }
+        for path in head.keys() {
+            if !files.contains_key(path) {
+                re.staged.push((relative_name(root_path, path), FileStatus::Deleted));
+            }
+        }
+        re.staged.sort();
+        self.scan_worktree(root_path, &index, &self.load_ignore()?, &mut re)?;
+        re.unstaged.sort();
+        // 没有解决冲突的文件只显示为unmerged
+        re.unmerged = self.read_conflicts()?.iter().map(|path| relative_name(root_path, path)).collect();
+        re.staged.retain(|(name, _)| !re.unmerged.contains(name));
+        re.unstaged.retain(|(name, _)| !re.unmerged.contains(name));
+        Ok(re)
+    }
+
+
+    /**
+     * 逐行合并文件path的内容，返回保存了合并结果的blob的key
+     */
+    fn merge_blob(&self, branch_name: &str, path: &Path, blobs_key: &[String], conflicts: &mut Vec<PathBuf>)-> Result<String> {
+        let mut blobs_value: Vec<Vec<u8>> = Vec::new();
+        for key in blobs_key {
+            blobs_value.push(self.read_blob(key)?.data);
+        }
+        // 共同祖先中没有这个文件时，以空文件作为共同祖先
+        if blobs_value.len() < 3 {
+            blobs_value.push(Vec::new());
+        }
+        // 合并文件数据，并且只在双方修改重叠的地方标出冲突
+        let now_branch_name = self.head()?.name().to_string();
+        let (file_value, no_conflict) = diff::merge3(&blobs_value[2], &blobs_value[0], &blobs_value[1],
+                                                     [&now_branch_name, "common ancestor", branch_name]);
+        if !no_conflict {
+            conflicts.push(path.to_path_buf());
+        }
+        self.save_value(&Blob { data: file_value }.serialize(self.format))
+    }
+
+    /**
+     * 合并至少有一方是符号链接的同名项，符号链接不能逐行合并
+     * 只有一方修改时使用修改后的一项，双方都修改时保留当前分支的一项并记为冲突
+     */
+    fn merge_symlink(&self, path: &Path, entries: [&TreeEntry; 2], base: Option<&TreeEntry>, conflicts: &mut Vec<PathBuf>)-> TreeEntry {
+        let same = |x: &TreeEntry, y: &TreeEntry| x.kind == y.kind && x.key == y.key;
+        let [ours, theirs] = entries;
+        if same(ours, theirs) || base.is_some_and(|base| same(base, theirs)) {
+            ours.clone()
+        }
+        else if base.is_some_and(|base| same(base, ours)) {
+            theirs.clone()
+        }
+        else {
+            conflicts.push(path.join(&ours.name));
+            ours.clone()
+        }
+    }
+
+    /**
+     * 三路合并文件夹path对应的tree，trees_key依次为当前分支、要合并的分支和共同祖先（可以没有）中的tree
+     * 只生成并保存合并后的对象而不修改工作区，返回合并后的tree的key，冲突的文件保存为标出冲突后的内容
+     */
+    fn merge_tree(&self, branch_name: &str, path: &Path, trees_key: &[String], conflicts: &mut Vec<PathBuf>)-> Result<String> {
+        let common = match trees_key.get(2) {
+            Some(key)=> self.read_tree(key)?,
+            None=> Tree::default(),
+        };
+        // 同名的文件和符号链接视为同一项，只有文件夹与它们分开比较
+        let is_tree = |entry: &TreeEntry| entry.kind == EntryKind::Tree;
+        let order = |x: &TreeEntry, y: &TreeEntry| x.name.cmp(&y.name).then(is_tree(x).cmp(&is_tree(y)));
+        let base = |entry: &TreeEntry| common.entries.iter().find(|c| c.name == entry.name && is_tree(c) == is_tree(entry));
+        let same = |x: &TreeEntry, y: &TreeEntry| x.kind == y.kind && x.key == y.key;
+        let mut trees = Vec::new();
+        for key in &trees_key[..2] {
+            let mut entries = self.read_tree(key)?.entries;
+            entries.sort_unstable_by(order);
+            trees.push(entries);
+        }
+        let (v1, v2) = (&trees[0], &trees[1]);
+        let (n1, n2)  = (v1.len(), v2.len());
+        let (mut it1, mut it2) = (0, 0);
+        let mut merged = Tree::default();
+        while it1 < n1 || it2 < n2 {
+            let ord = match (v1.get(it1), v2.get(it2)) {
+                (Some(e1), Some(e2))=> order(e1, e2),
+                (Some(_), None)=> Ordering::Less,
+                _=> Ordering::Greater,
+            };
+            match ord {
+                Ordering::Equal=> {
+                    let (e1, e2) = (&v1[it1], &v2[it2]);
+                    it1 += 1;
+                    it2 += 1;
+                    // 只有一方修改时直接使用修改后的一项，不用再读取其中的内容
+                    if same(e1, e2) || base(e1).is_some_and(|c| same(c, e2)) {
+                        merged.entries.push(e1.clone());
+                        continue;
+                    }
+                    if base(e1).is_some_and(|c| same(c, e1)) {
+                        merged.entries.push(e2.clone());
+                        continue;
+                    }
+                    let mut keys: Vec<String> = vec![e1.key.clone(), e2.key.clone()];
+                    // 普通文件和可执行文件的内容可以互相合并
+                    if let Some(c) = base(e1).filter(|c| c.kind == e1.kind || (c.kind.is_file() && e1.kind.is_file())) {
+                        keys.push(c.key.clone());
+                    }
+                    let child_path = path.join(&e1.name);
+                    let entry = match (e1.kind, e2.kind) {
+                        (EntryKind::Tree, _)=> TreeEntry { kind: EntryKind::Tree, key: self.merge_tree(branch_name, &child_path, &keys, conflicts)?, name: e1.name.clone() },
+                        (kind1, kind2) if kind1.is_file() && kind2.is_file()=> {
+                            // 文件模式也进行三路合并：当前分支没有修改模式时使用另一方的模式
+                            let kind = if base(e1).is_some_and(|c| c.kind == kind1) { kind2 } else { kind1 };
+                            TreeEntry { kind, key: self.merge_blob(branch_name, &child_path, &keys, conflicts)?, name: e1.name.clone() }
+                        },
+                        _=> self.merge_symlink(path, [e1, e2], base(e1), conflicts),
+                    };
+                    merged.entries.push(entry);
+                },
+                _=> {
+                    // 只在一方存在：如果共同祖先中有这一项，说明是被另一方删除了，不再保留
+                    let entry = if let Ordering::Less = ord { &v1[it1] } else { &v2[it2] };
+                    if let Ordering::Less = ord { it1 += 1; } else { it2 += 1; }
+                    if base(entry).is_none() {
+                        merged.entries.push(entry.clone());
+                    }
+                },
+            }
+        }
+        self.save_value(&merged.serialize(self.format))
+    }
+
+    /**
+     * 'read_conflicts'读取'.minigit/MERGE_CONFLICTS'中记录的还没有解决冲突的文件，没有进行中的合并时为空
+     * 文件中保存的是相对于仓库根目录的路径，以'\0'分隔
+     */
+    fn read_conflicts(&self)-> Result<BTreeSet<PathBuf>> {
+        let data = match fs::read(self.minigit_path.join("MERGE_CONFLICTS")) {
+            Ok(data)=> data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound=> return Ok(BTreeSet::new()),
+            Err(err)=> return Err(err.into()),
+        };
+        data.split(|b| *b == 0).filter(|name| !name.is_empty()).map(|name| {
+            let name = bytes_to_os_string(name).ok_or_else(|| Error::CorruptIndex(format!("invaild conflict path {:?}", String::from_utf8_lossy(name))))?;
+            Ok(self.root_path.join(name))
+        }).collect()
+    }
+
+    /**
+     * 'write_conflicts'覆盖'.minigit/MERGE_CONFLICTS'，conflicts为空时删除这个文件
+     */
+    fn write_conflicts(&self, conflicts: &BTreeSet<PathBuf>)-> Result<()> {
+        let path = self.minigit_path.join("MERGE_CONFLICTS");
+        if conflicts.is_empty() {
+            if path.is_file() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        let mut data = Vec::new();
+        for conflict in conflicts {
+            let name = conflict.strip_prefix(&self.root_path).unwrap_or(conflict);
+            data.extend_from_slice(name.as_os_str().as_encoded_bytes());
+            data.push(0);
+        }
+        let mut lock = LockFile::acquire(&path)?;
+        lock.write_all(&data)?;
+        lock.commit()
+    }
+
+    /**
+     * 'mark_resolved'把paths下的冲突文件标记为已解决，在'add'、'rm'和'rm_cached'之后调用
+     */
+    fn mark_resolved(&self, paths: &[PathBuf])-> Result<()> {
+        let mut conflicts = self.read_conflicts()?;
+        let count = conflicts.len();
+        conflicts.retain(|conflict| !paths.iter().any(|path| conflict.starts_with(path)));
+        if conflicts.len() != count {
+            self.write_conflicts(&conflicts)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * 'merge'将分支branch_name（也可以是其他版本表达式）合并到当前分支，需要新建合并提交时使用author作为作者
+     * 与'checkout'相同，合并会破坏没有提交的修改或者未跟踪的文件时返回Error::WouldOverwrite，与合并无关的修改保留下来
+     * 与git相同，暂存区中有修改时也返回Error::WouldOverwrite
+     */
+    pub fn merge(&self, branch_name: &str, author: &str)-> Result<MergeResult> {
+        self.merge_branch(branch_name, author, false)
+    }
+
+    /**
+     * 'force_merge'与'merge'相同，但是会丢弃没有提交的修改，并删除与要合并的分支中的文件冲突的未跟踪文件
+     */
+    pub fn force_merge(&self, branch_name: &str, author: &str)-> Result<MergeResult> {
+        self.merge_branch(branch_name, author, true)
+    }
+
+    fn merge_branch(&self, branch_name: &str, author: &str, force: bool)-> Result<MergeResult> {
+        let head = self.head()?;
+        if head == Head::Branch(branch_name.to_string()) {
+            return Ok(MergeResult::UpToDate)
+        }
+        // 分离状态时合并到HEAD指向的提交上
+        let now_branch_name = head.name().to_string();
+        let commit_key = self.resolve_commit(branch_name)?;
+        let now_commit_key = self.head_commit()?;
+        let bases = self.find_merge_bases(&commit_key, &now_commit_key)?;
+        if bases.is_empty() {
+            return Err(Error::NoCommonAncestor(branch_name.to_string(), now_branch_name));
+        }
+        // 如果没有分支，则快速合并，将指针移动到最新提交即可；一方是另一方的祖先时只有一个公共祖先
+        if bases[0] == commit_key {
+            // 说明此时已经在最新提交上，不用操作直接返回
+            return Ok(MergeResult::UpToDate)
+        }
+        if bases[0] == now_commit_key {
+            // 说明此时要合并的分支比现在的分支进度更远，将指针移动到要合并的分支的最新提交，并重建工作区和暂存区
+            let tree_key = self.get_tree_key_from_commit(&commit_key)?;
+            let mut ref_lock = LockFile::acquire(&self.head_ref_path(&head))?;
+            self.reset_worktree(&tree_key, force)?;
+            ref_lock.write_all(commit_key.as_bytes())?;
+            ref_lock.commit()?;
+            let reason = format!("merge {branch_name}: Fast-forward");
+            self.log_head_update(&head, Some(&now_commit_key), Some(&commit_key), author, &reason)?;
+            return Ok(MergeResult::FastForward(commit_key))
+        }
+        // 如果有分支，则需要三路合并
+        // 获得两个分支和共同祖先的tree-key，有多个最近公共祖先时使用它们合并后的tree
+        let trees_key = vec![
+            self.get_tree_key_from_commit(&now_commit_key)?,
+            self.get_tree_key_from_commit(&commit_key)?,
+            self.merge_base_tree(&bases)?,
+        ];
+        // 先生成合并后的tree，冲突的文件保存为标出冲突后的内容
+        let mut conflicts = Vec::new();
+        let merged_key = self.merge_tree(branch_name, &self.root_path, &trees_key, &mut conflicts)?;
+        // 与git相同，暂存区中有修改时不能合并，否则这些修改会被记录到合并提交中
+        if !force {
+            let staged = self.status()?.staged;
+            if !staged.is_empty() {
+                return Err(Error::WouldOverwrite(staged.iter().map(|(name, _)| self.root_path.join(name)).collect()));
+            }
+        }
+        // 与checkout相同，只重写当前分支与合并结果中不同的文件，其他文件上没有暂存的修改保留下来，没有冲突的修改同时被暂存
+        self.reset_worktree(&merged_key, force)?;
+        // 有冲突时记录冲突的文件，它们都被标记为已解决之后才能提交
+        self.write_conflicts(&conflicts.iter().cloned().collect())?;
+        if !conflicts.is_empty() {
+            // 有冲突时记录被合并的提交，解决冲突后的下一次提交会把它作为父提交
+            self.write_ref(&self.minigit_path.join("MERGE_HEAD"), &commit_key)?;
+            return Ok(MergeResult::Conflict(conflicts));
+        }
+        // 提交合并后的工作目录，合并提交同时以两个分支的最新提交为父提交
+        let key = self.commit_with_parents(author, &format!("merge {branch_name} to {now_branch_name}"), &[commit_key])?;
+        Ok(MergeResult::Merged(key))
+    }
+}
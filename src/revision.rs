@@ -0,0 +1,109 @@
+//! minigit 版本表达式：将"master~2"、"v1.0^2"、"HEAD@{1}"、"HEAD:src/main.rs"等写法解析为名字和之后依次进行的操作
+
+use crate::error::{Error, Result};
+
+/**
+ * 版本表达式中名字之后的一步操作
+ * - Ancestor(n)：'~n'，沿着第一个父提交向上n代，'~'等于'~1'
+ * - Parent(n)：'^n'，第n个父提交，'^'等于'^1'，'^0'为提交本身
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionStep {
+    Ancestor(usize),
+    Parent(usize),
+}
+
+/**
+ * 解析后的版本表达式 "名字@{n}~n^n:路径"，除了名字以外的部分都可以省略
+ * - name：HEAD、分支名字、标签名字、完整的或者缩写的key，'@'等于HEAD；只有'@{n}'时为空，表示当前分支
+ * - reflog：'@{n}'，名字的引用日志中第n条记录（最新的为0）之后指向的提交，只能紧跟在名字后面
+ * - steps：依次进行的'~n'和'^n'
+ * - path：':'之后的路径，表示提交中的文件或者文件夹，路径相对于仓库根目录，为空时表示根目录的tree
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    pub name: String,
+    pub reflog: Option<usize>,
+    pub steps: Vec<RevisionStep>,
+    pub path: Option<String>,
+}
+
+/**
+ * 读取text开头的十进制数字，返回数字（没有数字时为None）和剩余的部分
+ */
+fn parse_number(text: &str) -> Result<(Option<usize>, &str)> {
+    let end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    if end == 0 {
+        return Ok((None, text));
+    }
+    let n = text[..end].parse().map_err(|_| Error::Usage(format!("invaild number {}", &text[..end])))?;
+    Ok((Some(n), &text[end..]))
+}
+
+impl Revision {
+    /**
+     * 'parse'解析版本表达式，格式错误时返回Error::Usage
+     * # 示例
+     * ```
+        use minigit::revision::{Revision, RevisionStep};
+        let revision = Revision::parse("master~2^2:src/main.rs").unwrap();
+        assert_eq!(revision.name, "master");
+        assert_eq!(revision.steps, vec![RevisionStep::Ancestor(2), RevisionStep::Parent(2)]);
+        assert_eq!(revision.path.as_deref(), Some("src/main.rs"));
+     * ```
+     */
+    pub fn parse(text: &str) -> Result<Revision> {
+        let invaild = || Error::Usage(format!("invaild revision {text}"));
+        let (rev, path) = match text.split_once(':') {
+            Some((rev, path)) => (rev, Some(path.to_string())),
+            None => (text, None),
+        };
+        let end = [rev.find('~'), rev.find('^'), rev.find("@{")].into_iter().flatten().min().unwrap_or(rev.len());
+        let (name, mut rest) = rev.split_at(end);
+        let mut reflog = None;
+        if let Some(inner) = rest.strip_prefix("@{") {
+            let (n, after) = parse_number(inner)?;
+            rest = after.strip_prefix('}').ok_or_else(invaild)?;
+            reflog = Some(n.ok_or_else(invaild)?);
+        }
+        if name.is_empty() && reflog.is_none() {
+            return Err(invaild());
+        }
+        let mut steps = Vec::new();
+        while let Some(c) = rest.chars().next() {
+            if c != '~' && c != '^' {
+                return Err(invaild());
+            }
+            let (n, after) = parse_number(&rest[1..])?;
+            steps.push(if c == '~' { RevisionStep::Ancestor(n.unwrap_or(1)) } else { RevisionStep::Parent(n.unwrap_or(1)) });
+            rest = after;
+        }
+        let name = if name == "@" { "HEAD" } else { name };
+        Ok(Revision { name: name.to_string(), reflog, steps, path })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let revision = Revision::parse("HEAD").unwrap();
+        assert_eq!(revision, Revision { name: "HEAD".to_string(), reflog: None, steps: Vec::new(), path: None });
+        assert_eq!(Revision::parse("@").unwrap().name, "HEAD");
+        let revision = Revision::parse("dev@{3}~^0:").unwrap();
+        assert_eq!(revision.name, "dev");
+        assert_eq!(revision.reflog, Some(3));
+        assert_eq!(revision.steps, vec![RevisionStep::Ancestor(1), RevisionStep::Parent(0)]);
+        assert_eq!(revision.path.as_deref(), Some(""));
+        let revision = Revision::parse("@{1}").unwrap();
+        assert_eq!((revision.name.as_str(), revision.reflog), ("", Some(1)));
+        assert!(Revision::parse("").is_err());
+        assert!(Revision::parse("~1").is_err());
+        assert!(Revision::parse("HEAD@{x}").is_err());
+        assert!(Revision::parse("HEAD~1@{1}").is_err());
+        assert!(Revision::parse("HEAD^x").is_err() && Revision::parse("HEAD~1é").is_err());
+    }
+}